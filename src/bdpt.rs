@@ -0,0 +1,527 @@
+use crate::{
+    bsdf::{sample_uniforms, Bsdf, BsdfFlags},
+    camera::Camera,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    onb::ONB,
+    random_double,
+    ray::Ray,
+    spectrum::{sample_wavelength, Channels, Wavelengths},
+    render::{film_pixel, film_position, film_to_rgb, Background, Framebuffer, RenderSettings},
+    utils::{INFINITY, PI},
+    vec3::{dot, random_cosine_direction, unit_vector, Color, Point3, Vec3},
+};
+use rayon::prelude::*;
+
+// Shadow rays stop this fraction short of either endpoint.
+const SHADOW_EPSILON: f64 = 1e-4;
+
+/// What a subpath carries: radiance from the camera side, which may escape
+/// to the background, or importance from a light.
+#[derive(Clone, Copy)]
+enum Transport<'a> {
+    Radiance(&'a Background),
+    Importance,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+/// A vertex of a camera or light subpath.
+///
+/// `pdf_fwd` is the area density of sampling this vertex from the previous
+/// one along its own subpath, `pdf_rev` that of sampling it the other way
+/// round, from the next vertex. Both are zero across specular bounces.
+struct Vertex {
+    kind: VertexKind,
+    p: Point3,
+    /// Shading normal of surfaces and lights, viewing direction of the camera.
+    n: Vec3,
+    /// Unit direction toward the previous vertex on the subpath.
+    wo: Vec3,
+    rec: Option<HitRecord>,
+    /// Radiance emitted by a light vertex.
+    le: Color,
+    beta: Color,
+    delta: bool,
+    /// On a light subpath, where BSDFs are evaluated with the directions
+    /// swapped, as light flows from `wo` to the next vertex.
+    importance: bool,
+    /// Whether the subpath passed a dispersive bounce before reaching here.
+    dispersed: bool,
+    pdf_fwd: f64,
+    pdf_rev: f64,
+}
+
+impl Vertex {
+    fn camera(cam: &Camera) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            p: cam.origin(),
+            n: cam.forward(),
+            wo: Vec3::zero(),
+            rec: None,
+            le: Color::zero(),
+            beta: Color::ones(),
+            delta: false,
+            importance: false,
+            dispersed: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn is_on_surface(&self) -> bool {
+        self.kind != VertexKind::Camera
+    }
+
+    fn is_connectible(&self) -> bool {
+        !self.delta
+    }
+
+    /// Scattered or emitted radiance per unit incident radiance toward `next`.
+    fn f(&self, next: &Vertex) -> Color {
+        let wi = unit_vector(next.p - self.p);
+        match &self.rec {
+            Some(rec) if self.importance => rec.material().eval(rec, wi, self.wo),
+            Some(rec) => rec.material().eval(rec, self.wo, wi),
+            None => self.le,
+        }
+    }
+
+    /// Area density at `next` of continuing the subpath from here, having
+    /// arrived from `prev`.
+    fn pdf(&self, cam: &Camera, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+        let w = next.p - self.p;
+        let pdf = match (self.kind, &self.rec, prev) {
+            (VertexKind::Light, ..) => return self.pdf_light(next),
+            (VertexKind::Camera, ..) => cam.pdf_direction(w),
+            (_, Some(rec), Some(prev)) => rec.material().pdf(rec, unit_vector(prev.p - self.p), unit_vector(w)),
+            _ => 0.0,
+        };
+        convert_density(pdf, self, next)
+    }
+
+    /// Area density at `next` of emitting toward it from this point of a
+    /// light, which shines from both sides with a cosine distribution.
+    fn pdf_light(&self, next: &Vertex) -> f64 {
+        let w = next.p - self.p;
+        let dist2 = w.length_squared();
+        if dist2 == 0.0 {
+            return 0.0
+        }
+        let wn = w / dist2.sqrt();
+        let mut pdf = dot(&self.n, &wn).abs() / (2.0 * PI) / dist2;
+        if next.is_on_surface() {
+            pdf *= dot(&next.n, &wn).abs();
+        }
+        pdf
+    }
+}
+
+/// Turn a solid-angle density at `from` into an area density at `next`.
+fn convert_density(pdf: f64, from: &Vertex, next: &Vertex) -> f64 {
+    let w = next.p - from.p;
+    let dist2 = w.length_squared();
+    if dist2 == 0.0 {
+        return 0.0
+    }
+    let mut pdf = pdf / dist2;
+    if next.is_on_surface() {
+        pdf *= dot(&next.n, &(w / dist2.sqrt())).abs();
+    }
+    pdf
+}
+
+/// Extend `path` from its last vertex along `ray` by at most `max_vertices`
+/// surface hits, returning the radiance of the background the walk escapes
+/// to, if any, weighted by its throughput.
+fn random_walk<H: Hittable>(
+    world: &H,
+    mut ray: Ray,
+    mut beta: Color,
+    pdf_dir: f64,
+    max_vertices: usize,
+    transport: Transport,
+    path: &mut Vec<Vertex>,
+) -> Color {
+    let mut pdf_fwd = pdf_dir;
+    for _ in 0..max_vertices {
+        let mut rec = HitRecord::new();
+        if !world.hit(&ray, 0.001, INFINITY, &mut rec) {
+            return match transport {
+                Transport::Radiance(bg) if ray.dispersed => beta * bg.value(&ray) * ray.dispersion_weight(),
+                Transport::Radiance(bg) => beta * bg.value(&ray),
+                Transport::Importance => Color::zero(),
+            }
+        }
+        let prev = path.len() - 1;
+        let wo = -unit_vector(ray.direction);
+        // Scattering inside a subsurface medium leaves a delta vertex, as
+        // the walk there cannot be connected to.
+        let event = rec.material().medium_event(&ray, &rec);
+        beta *= event.weight;
+        if let Some((p, direction)) = event.scattered {
+            path.push(Vertex {
+                kind: VertexKind::Surface,
+                p,
+                n: wo,
+                wo,
+                rec: None,
+                le: Color::zero(),
+                beta,
+                delta: true,
+                importance: matches!(transport, Transport::Importance),
+                dispersed: ray.dispersed,
+                pdf_fwd: 0.0,
+                pdf_rev: 0.0,
+            });
+            path[prev].pdf_rev = 0.0;
+            pdf_fwd = 0.0;
+            ray = ray.spawn(p, direction);
+            ray.walk = event.walk;
+            continue
+        }
+        let sample = rec.material().sample(&rec, wo, sample_uniforms());
+        let specular = sample.as_ref().is_some_and(|s| s.flags.is_specular());
+        let (pdf_next, pdf_rev) = match &sample {
+            Some(s) if !specular => (s.pdf, rec.material().pdf(&rec, s.wi, wo)),
+            _ => (0.0, 0.0),
+        };
+        // Importance scatters through the adjoint BSDF, which differs from
+        // the sampled weight for refraction.
+        let weight = match (&sample, transport) {
+            (Some(s), Transport::Importance) if !specular => {
+                rec.material().eval(&rec, s.wi, wo) * (dot(&rec.normal, &s.wi).abs() / s.pdf)
+            }
+            (Some(s), _) => s.weight,
+            (None, _) => Color::zero(),
+        };
+
+        let mut vertex = Vertex {
+            kind: VertexKind::Surface,
+            p: rec.p,
+            n: rec.normal,
+            wo,
+            rec: None,
+            le: Color::zero(),
+            beta,
+            delta: specular,
+            importance: matches!(transport, Transport::Importance),
+            dispersed: ray.dispersed,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        };
+        vertex.pdf_fwd = convert_density(pdf_fwd, &path[prev], &vertex);
+        path[prev].pdf_rev = convert_density(pdf_rev, &vertex, &path[prev]);
+        let p = rec.p;
+        vertex.rec = Some(rec);
+        path.push(vertex);
+        let sample = match sample {
+            Some(s) if specular || s.pdf > 0.0 => s,
+            _ => break,
+        };
+
+        beta *= weight;
+        pdf_fwd = pdf_next;
+        ray = ray.spawn(p, sample.wi);
+        ray.walk = event.walk;
+        // The dispersion weight is applied once the subpaths are joined, as
+        // either may have been dispersed.
+        if sample.flags.contains(BsdfFlags::DISPERSIVE) {
+            ray.dispersed = true;
+        }
+    }
+    Color::zero()
+}
+
+/// Camera subpath through film coordinates `(s, t)` at the wavelengths of
+/// `like`, plus the background radiance it escapes to.
+fn camera_subpath<H: Hittable>(
+    world: &H,
+    cam: &Camera,
+    background: &Background,
+    (s, t): (f64, f64),
+    like: &Ray,
+    max_depth: usize,
+) -> (Vec<Vertex>, Color) {
+    let mut path = vec![Vertex::camera(cam)];
+    let pinhole = cam.get_pinhole_ray(s, t);
+    let ray = like.spawn(pinhole.origin, pinhole.direction);
+    let pdf_dir = cam.pdf_direction(ray.direction);
+    let escaped = random_walk(world, ray, Color::ones(), pdf_dir, max_depth + 1, Transport::Radiance(background), &mut path);
+    (path, escaped)
+}
+
+/// Light subpath at the wavelengths of `like` starting at a uniformly chosen
+/// light of `lights`.
+fn light_subpath<H: Hittable>(world: &H, lights: &HittableList, like: &Ray, max_depth: usize) -> Vec<Vertex> {
+    let n = lights.objects.len();
+    if n == 0 {
+        return Vec::new()
+    }
+    let light = &lights.objects[((random_double!() * n as f64) as usize).min(n - 1)];
+    let area = light.area();
+    if area <= 0.0 {
+        return Vec::new()
+    }
+    let (p, normal) = light.random_point();
+    let mut light_rec = HitRecord::new();
+    if !light.hit(&like.spawn(p + normal, -normal), 0.5, 1.5, &mut light_rec) {
+        return Vec::new()
+    }
+    let le = light_rec.emitted();
+
+    let pdf_pos = 1.0 / (n as f64 * area);
+    let side = if random_double!() < 0.5 { normal } else { -normal };
+    let local = random_cosine_direction();
+    let pdf_dir = local.z / (2.0 * PI);
+    let origin = Vertex {
+        kind: VertexKind::Light,
+        p,
+        n: normal,
+        wo: Vec3::zero(),
+        rec: None,
+        le,
+        beta: Color::ones() / pdf_pos,
+        delta: false,
+        importance: true,
+        dispersed: false,
+        pdf_fwd: pdf_pos,
+        pdf_rev: 0.0,
+    };
+    let beta = le * (local.z / (pdf_pos * pdf_dir));
+    let ray = like.spawn(p, ONB::build_from_w(side).local(local));
+    let mut path = vec![origin];
+    random_walk(world, ray, beta, pdf_dir, max_depth, Transport::Importance, &mut path);
+    path
+}
+
+fn visible<H: Hittable>(world: &H, a: &Vertex, b: &Vertex) -> bool {
+    let mut rec = HitRecord::new();
+    !world.hit(&Ray::new(a.p, b.p - a.p, 0.0), SHADOW_EPSILON, 1.0 - SHADOW_EPSILON, &mut rec)
+}
+
+/// Geometry term between two vertices, zero if they cannot see each other.
+fn geometry<H: Hittable>(world: &H, a: &Vertex, b: &Vertex) -> f64 {
+    let w = b.p - a.p;
+    let dist2 = w.length_squared();
+    if dist2 == 0.0 || !visible(world, a, b) {
+        return 0.0
+    }
+    let wn = w / dist2.sqrt();
+    let mut g = 1.0 / dist2;
+    if a.is_on_surface() {
+        g *= dot(&a.n, &wn).abs();
+    }
+    if b.is_on_surface() {
+        g *= dot(&b.n, &wn).abs();
+    }
+    g
+}
+
+/// Area density of `light_subpath` starting at `v`, an emitter reached from
+/// `prev`.
+fn pdf_light_origin(lights: &HittableList, v: &Vertex, prev: &Vertex) -> f64 {
+    let n = lights.objects.len();
+    let mut rec = HitRecord::new();
+    let r = Ray::new(prev.p, v.p - prev.p, 0.0);
+    lights
+        .objects
+        .iter()
+        .find(|light| light.hit(&r, 1.0 - SHADOW_EPSILON, 1.0 + SHADOW_EPSILON, &mut rec))
+        .map_or(0.0, |light| 1.0 / (n as f64 * light.area()))
+}
+
+/// Balance heuristic weight of the strategy joining `s` light and `t`
+/// camera vertices, against every other way of sampling the same path.
+fn mis_weight(lights: &HittableList, cam: &Camera, light_path: &[Vertex], camera_path: &[Vertex], s: usize, t: usize) -> f64 {
+    if s + t == 2 {
+        return 1.0
+    }
+    // (pdf_fwd, pdf_rev, delta) of each vertex, with the densities around
+    // the connection replaced by those of sampling it from the other side.
+    let mut camera_pdfs: Vec<(f64, f64, bool)> = camera_path[..t].iter().map(|v| (v.pdf_fwd, v.pdf_rev, v.delta)).collect();
+    let mut light_pdfs: Vec<(f64, f64, bool)> = light_path[..s].iter().map(|v| (v.pdf_fwd, v.pdf_rev, v.delta)).collect();
+    let pt = &camera_path[t - 1];
+    let pt_minus = if t > 1 { Some(&camera_path[t - 2]) } else { None };
+    let qs = if s > 0 { Some(&light_path[s - 1]) } else { None };
+    let qs_minus = if s > 1 { Some(&light_path[s - 2]) } else { None };
+
+    camera_pdfs[t - 1].2 = false;
+    camera_pdfs[t - 1].1 = match (qs, pt_minus) {
+        (Some(qs), _) => qs.pdf(cam, qs_minus, pt),
+        (None, Some(pm)) => pdf_light_origin(lights, pt, pm),
+        (None, None) => 0.0,
+    };
+    if let Some(pm) = pt_minus {
+        camera_pdfs[t - 2].1 = match qs {
+            Some(qs) => pt.pdf(cam, Some(qs), pm),
+            None => pt.pdf_light(pm),
+        };
+    }
+    if let Some(qs) = qs {
+        light_pdfs[s - 1].2 = false;
+        light_pdfs[s - 1].1 = pt.pdf(cam, pt_minus, qs);
+    }
+    if let (Some(qs), Some(qm)) = (qs, qs_minus) {
+        light_pdfs[s - 2].1 = qs.pdf(cam, Some(pt), qm);
+    }
+
+    // Delta densities are zero; they cancel in the ratios, so count them as one.
+    let remap = |pdf: f64| if pdf != 0.0 { pdf } else { 1.0 };
+    let mut sum_ri = 0.0;
+    let mut ri = 1.0;
+    for i in (1..t).rev() {
+        ri *= remap(camera_pdfs[i].1) / remap(camera_pdfs[i].0);
+        if !camera_pdfs[i].2 && !camera_pdfs[i - 1].2 {
+            sum_ri += ri;
+        }
+    }
+    ri = 1.0;
+    for i in (0..s).rev() {
+        ri *= remap(light_pdfs[i].1) / remap(light_pdfs[i].0);
+        let delta_prev = i > 0 && light_pdfs[i - 1].2;
+        if !light_pdfs[i].2 && !delta_prev {
+            sum_ri += ri;
+        }
+    }
+    1.0 / (1.0 + sum_ri)
+}
+
+/// Contribution of joining the first `s` light and `t` camera vertices,
+/// and for `t == 1` the film position it lands on.
+#[allow(clippy::too_many_arguments)]
+fn connect<H: Hittable>(
+    world: &H,
+    lights: &HittableList,
+    cam: &Camera,
+    light_path: &[Vertex],
+    camera_path: &[Vertex],
+    s: usize,
+    t: usize,
+) -> (Color, Option<(f64, f64)>) {
+    let pt = &camera_path[t - 1];
+    let mut film = None;
+    let l = if s == 0 {
+        match &pt.rec {
+            Some(rec) => pt.beta * rec.emitted(),
+            None => Color::zero(),
+        }
+    } else if t == 1 {
+        // Light tracing: project the light vertex straight onto the film.
+        let qs = &light_path[s - 1];
+        if !qs.is_connectible() {
+            return (Color::zero(), None)
+        }
+        let w = pt.p - qs.p;
+        match cam.importance(-w) {
+            Some((fs, ft, we)) if visible(world, qs, pt) => {
+                film = Some((fs, ft));
+                let dist2 = w.length_squared();
+                let wn = w / dist2.sqrt();
+                let cos_film = dot(&pt.n, &wn).abs();
+                qs.beta * qs.f(pt) * (we * cos_film * dot(&qs.n, &wn).abs() / dist2)
+            }
+            _ => Color::zero(),
+        }
+    } else {
+        let qs = &light_path[s - 1];
+        if !qs.is_connectible() || !pt.is_connectible() {
+            return (Color::zero(), None)
+        }
+        let l = qs.beta * qs.f(pt) * pt.f(qs) * pt.beta;
+        if l.x == 0.0 && l.y == 0.0 && l.z == 0.0 {
+            return (Color::zero(), None)
+        }
+        l * geometry(world, qs, pt)
+    };
+    if l.x == 0.0 && l.y == 0.0 && l.z == 0.0 {
+        return (Color::zero(), None)
+    }
+    (l * mis_weight(lights, cam, light_path, camera_path, s, t), film)
+}
+
+/// Bidirectional path tracing (Veach 1997).
+///
+/// Every sample traces a camera subpath and a light subpath of up to
+/// `max_depth` bounces and joins each prefix pair, weighting the strategies
+/// with the balance heuristic. Joins through the camera vertex land on
+/// arbitrary pixels and are splatted. The camera is treated as a pinhole,
+/// without defocus blur or motion blur, and emitters must be in `lights`.
+pub fn render<H: Hittable + Sync>(settings: &RenderSettings, lights: &HittableList, cam: &Camera, world: &H) -> Framebuffer {
+    let mut fb = Framebuffer::new(settings.image_width, settings.image_height);
+    let (w, h) = (settings.image_width as usize, settings.image_height as usize);
+    let pixels = w * h;
+    let spp = settings.samples_per_pixel;
+    let max_depth = settings.max_depth;
+
+    let (colors, splats) = (0..pixels)
+        .into_par_iter()
+        .fold(
+            || (Vec::new(), vec![Color::zero(); pixels]),
+            |(mut colors, mut splats), idx| {
+                // Framebuffer rows run top to bottom, film `t` bottom to top.
+                let (row, col) = (idx / w, idx % w);
+                let mut sum = Color::zero();
+                for _ in 0..spp {
+                    let (fs, ft) =
+                        film_position(settings, col as f64 + random_double!(), (h - 1 - row) as f64 + random_double!());
+                    // Both subpaths share their wavelengths so that they can be joined.
+                    let mut like = Ray::new(Point3::zero(), Vec3::zero(), 0.0);
+                    if settings.spectral {
+                        like.channels = Channels::Spectrum(Wavelengths::sample(random_double!()));
+                    } else {
+                        like.channels = Channels::Wavelength(sample_wavelength(random_double!()));
+                    }
+                    let (camera_path, escaped) =
+                        camera_subpath(world, cam, &settings.background, (fs, ft), &like, max_depth);
+                    let light_path = light_subpath(world, lights, &like, max_depth);
+                    sum += like.film(escaped);
+                    for t in 1..=camera_path.len() {
+                        for s in 0..=light_path.len() {
+                            if s + t < 2 || s + t - 2 > max_depth || (s == 1 && t == 1) {
+                                continue
+                            }
+                            let (mut l, film) = connect(world, lights, cam, &light_path, &camera_path, s, t);
+                            if camera_path[t - 1].dispersed || (s > 0 && light_path[s - 1].dispersed) {
+                                l *= like.dispersion_weight();
+                            }
+                            let l = like.film(l);
+                            match film {
+                                Some((fs, ft)) => splats[film_pixel(settings, fs, ft)] += l,
+                                None => sum += l,
+                            }
+                        }
+                    }
+                }
+                colors.push((idx, sum));
+                (colors, splats)
+            },
+        )
+        .reduce(
+            || (Vec::new(), vec![Color::zero(); pixels]),
+            |(mut c0, mut s0), (c1, s1)| {
+                c0.extend(c1);
+                for (x, y) in s0.iter_mut().zip(s1) {
+                    *x += y;
+                }
+                (c0, s0)
+            },
+        );
+
+    for (idx, c) in colors {
+        fb.data[idx] = c;
+    }
+    // Splats land on any pixel, so spreading them over the whole image takes
+    // `spp` samples of every pixel just like the camera estimates.
+    let scale = 1.0 / spp as f64;
+    for (x, splat) in fb.data.iter_mut().zip(splats) {
+        *x = film_to_rgb(settings, (*x + splat) * scale);
+    }
+    fb
+}
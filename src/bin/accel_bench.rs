@@ -0,0 +1,191 @@
+use rayt::{
+    bvh::BvhNode,
+    hittable_list::HittableList,
+    kdtree::KdTree,
+    linear_bvh::LinearBvh,
+    octree::Octree,
+    camera::Camera,
+    hittable::{HitRecord, Hittable, Shape},
+    material::Material,
+    ray::Ray,
+    utils::INFINITY,
+    vec3::{random_unit_vector, Color, Point3, Vec3},
+};
+
+use std::time::Instant;
+use image::GenericImageView;
+
+extern crate ply_rs;
+use ply_rs::ply;
+use ply_rs::parser;
+
+struct Face {
+    vertex_index: Vec<i32>,
+}
+
+impl ply::PropertyAccess for Face {
+    fn new() -> Self {
+        Face {
+            vertex_index: Vec::new(),
+        }
+    }
+    fn set_property(&mut self, key: String, property: ply::Property) {
+        match (key.as_ref(), property) {
+            ("vertex_index", ply::Property::ListInt(vec)) => self.vertex_index = vec,
+            ("vertex_indices", ply::Property::ListInt(vec)) => self.vertex_index = vec,
+            (k, _) => panic!("Face: Unexpected key/value combination: key: {}", k),
+        }
+    }
+}
+
+fn read_ply(path: &str, scale: f64, objects: &mut Vec<Shape>) {
+    let f = std::fs::File::open(path).unwrap();
+    let mut f = std::io::BufReader::new(f);
+
+    let vertex_parser = parser::Parser::<Point3>::new();
+    let face_parser = parser::Parser::<Face>::new();
+
+    let header = vertex_parser.read_header(&mut f).unwrap();
+
+    let mut vertex_list = Vec::new();
+    let mut face_list = Vec::new();
+    for (_ignore_key, element) in &header.elements {
+        match element.name.as_ref() {
+            "vertex" => {vertex_list = vertex_parser.read_payload_for_element(&mut f, element, &header).unwrap();},
+            "face" => {face_list = face_parser.read_payload_for_element(&mut f, element, &header).unwrap();},
+            _ => panic!("Enexpeced element!"),
+        }
+    }
+    let mat = Material::new_lambertian(Color::from([0.7, 0.2, 0.1]));
+    for fc in face_list.iter() {
+        objects.push(Shape::new_triangle(
+            vertex_list[fc.vertex_index[0] as usize] * scale,
+            vertex_list[fc.vertex_index[1] as usize] * scale,
+            vertex_list[fc.vertex_index[2] as usize] * scale,
+            mat.clone()));
+    }
+}
+
+fn bunny_scene() -> Vec<Shape> {
+    let mut objects = Vec::new();
+    let ground_material = Material::new_lambertian(Color::from([0.5, 0.5, 0.5]));
+    objects.push(Shape::new_triangle(Point3::from([1000.0,0.0,0.0]),Point3::from([0.0,0.0,-1000.0]),Point3::from([0.0,0.0,1000.0]),ground_material.clone()));
+    objects.push(Shape::new_triangle(Point3::from([0.0,0.0,-1000.0]),Point3::from([-1000.0,0.0,0.0]),Point3::from([0.0,0.0,1000.0]),ground_material));
+    read_ply("assets/bunny.ply", 10.0, &mut objects);
+    objects
+}
+
+fn key_scene() -> Vec<Shape> {
+    let mut objects = Vec::new();
+    let ground_material = Material::new_lambertian(Color::from([0.5, 0.5, 0.5]));
+    objects.push(Shape::new_triangle(Point3::from([1000.0,-0.5,0.0]),Point3::from([0.0,-0.5,-1000.0]),Point3::from([0.0,-0.5,1000.0]),ground_material.clone()));
+    objects.push(Shape::new_triangle(Point3::from([0.0,-0.5,-1000.0]),Point3::from([-1000.0,-0.5,0.0]),Point3::from([0.0,-0.5,1000.0]),ground_material));
+    read_ply("assets/key.ply", 0.05, &mut objects);
+    objects
+}
+
+/// Equal-sized spheres laid out from `assets/1.png`, as in `custom_img`.
+fn logo_scene() -> Vec<Shape> {
+    let mut objects = Vec::new();
+    let ground_material = Material::new_metal(Color::from([0.5, 0.5, 0.5]), 0.0);
+    objects.push(Shape::new_sphere(Point3::from([0.0, -1000.0, 0.0]), 1000.0, ground_material));
+    let sphere_material = Material::new_metal(Color::new(0.9, 0.2, 0.3), 0.0);
+    let image1 = image::open("assets/1.png").unwrap();
+    for a in 0..image1.width() {
+        for b in 0..image1.height() {
+            let pixel_color = image1.get_pixel(a, b);
+            if pixel_color[0]+pixel_color[1]+pixel_color[2] > 150 {
+                objects.push(Shape::new_sphere(
+                    Point3::new(a as f64 / 5.0 - 7.0, (image1.height() - b) as f64 / 5.0, 0.0),
+                    0.18,
+                    sphere_material.clone(),
+                ));
+            }
+        }
+    }
+    objects
+}
+
+/// Trace a primary ray per pixel plus one diffuse bounce from every hit.
+fn trace<H: Hittable>(world: &H, cam: &Camera) -> (usize, usize) {
+    let mut rays = 0;
+    let mut hits = 0;
+    for j in 0..IMAGE_HEIGHT {
+        for i in 0..IMAGE_WIDTH {
+            let u = i as f64 / (IMAGE_WIDTH - 1) as f64;
+            let v = j as f64 / (IMAGE_HEIGHT - 1) as f64;
+            let r = cam.get_ray(u, v);
+            let mut rec = HitRecord::new();
+            rays += 1;
+            if world.hit(&r, 0.001, INFINITY, &mut rec) {
+                hits += 1;
+                let bounce = Ray::new(rec.p, rec.normal + random_unit_vector(), 0.0);
+                rays += 1;
+                if world.hit(&bounce, 0.001, INFINITY, &mut rec) {
+                    hits += 1;
+                }
+            }
+        }
+    }
+    (rays, hits)
+}
+
+fn bench<H: Hittable>(name: &str, world: &H, build_secs: f64, cam: &Camera) {
+    let start = Instant::now();
+    let (rays, hits) = trace(world, cam);
+    let secs = start.elapsed().as_secs_f64();
+    println!(
+        "{:<12} build {:>7.3}s  trace {:>7.3}s  {:>8.3} Mrays/s  ({} rays, {} hits)",
+        name, build_secs, secs, rays as f64 / secs / 1e6, rays, hits
+    );
+}
+
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const IMAGE_WIDTH: u32 = 400;
+const IMAGE_HEIGHT: u32 = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as u32;
+
+fn camera(lookfrom: Point3, lookat: Point3) -> Camera {
+    Camera::new(lookfrom, lookat, Vec3::from([0.0, 1.0, 0.0]), 20.0, ASPECT_RATIO, 0.0, 10.0, 0.0, 0.0)
+}
+
+fn compare(scene: &str, objects: Vec<Shape>, cam: &Camera) {
+    println!("{}: {} primitives", scene, objects.len());
+
+    let start = Instant::now();
+    let mut median_objects = objects.clone();
+    let end = median_objects.len();
+    let median = BvhNode::new(&mut median_objects, 0, end);
+    bench("bvh-median", &median, start.elapsed().as_secs_f64(), cam);
+
+    let start = Instant::now();
+    let sah = BvhNode::new_sah(&objects);
+    bench("bvh-sah", &sah, start.elapsed().as_secs_f64(), cam);
+
+    let start = Instant::now();
+    let linear = LinearBvh::new(objects.clone());
+    bench("linear-bvh", &linear, start.elapsed().as_secs_f64(), cam);
+
+    let start = Instant::now();
+    let kdtree = KdTree::new(objects.clone());
+    bench("kdtree", &kdtree, start.elapsed().as_secs_f64(), cam);
+
+    for &(max_depth, leaf_size) in [(16, 4), (32, 1)].iter() {
+        let start = Instant::now();
+        let kdtree = KdTree::with_limits(objects.clone(), max_depth, leaf_size);
+        let name = format!("kdtree-{}-{}", max_depth, leaf_size);
+        bench(&name, &kdtree, start.elapsed().as_secs_f64(), cam);
+    }
+
+    let start = Instant::now();
+    let octree = Octree::new(HittableList { objects });
+    bench("octree", &octree, start.elapsed().as_secs_f64(), cam);
+}
+
+fn main() {
+    let bunny = camera(Point3::from([7.0, 3.5, -7.0]), Point3::from([0.0, 1.0, 0.0]));
+    compare("bunny", bunny_scene(), &bunny);
+    let key = camera(Point3::from([7.0, 3.0, -7.0]), Point3::from([0.0, -0.5, 0.0]));
+    compare("key", key_scene(), &key);
+    let logo = camera(Point3::from([22.0, 4.0, 22.0]), Point3::from([0.0, 2.0, -4.0]));
+    compare("logo", logo_scene(), &logo);
+}
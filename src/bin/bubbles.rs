@@ -0,0 +1,64 @@
+use rayt::{
+    camera::Camera,
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::Material,
+    render::{Background, Integrator, RenderSettings, Renderer},
+    texture::Surface,
+    thin_film::ThinFilm,
+    vec3::{Color, Point3, Vec3},
+};
+
+use std::io;
+
+/// Soap bubbles whose film thins and thickens in swirls, next to a steel
+/// ball under a thin oxide layer, on a plain floor under the sky.
+fn bubble_scene() -> HittableList {
+    let mut world = HittableList::default();
+    let ground_material = Material::new_lambertian(Color::from([0.5, 0.5, 0.5]));
+    world.add(Shape::new_quad(Point3::from([-20.0, 0.0, -20.0]), Vec3::from([40.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 40.0]), ground_material));
+
+    let soap = ThinFilm { thickness: Surface::new_noise_texture(2.0), max_thickness: 900.0, ior: 1.33 };
+    world.add(Shape::new_sphere(Point3::from([0.0, 1.2, 0.0]), 1.0, Material::new_thin_film_dielectric(1.0, soap.clone())));
+    world.add(Shape::new_sphere(Point3::from([-1.8, 0.8, 1.6]), 0.6, Material::new_thin_film_dielectric(1.0, soap)));
+
+    let oxide = ThinFilm::new(280.0, 2.4);
+    world.add(Shape::new_sphere(Point3::from([2.0, 0.7, -1.5]), 0.7, Material::new_thin_film_metal(Color::from([0.6, 0.6, 0.6]), 0.0, oxide)));
+    world
+}
+
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const IMAGE_WIDTH: u32 = 400;
+const SAMPLES_PER_PIXEL: usize = 64;
+const MAX_DEPTH: usize = 20;
+
+fn main() {
+    let world = bubble_scene();
+    let lookfrom = Point3::from([13.0, 4.0, 3.0]);
+    let lookat = Point3::from([0.0, 0.8, 0.0]);
+    let vup = Vec3::from([0.0, 1.0, 0.0]);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        25.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        0.0,
+    );
+
+    let mut settings = RenderSettings::new(IMAGE_WIDTH, ASPECT_RATIO, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    settings.integrator = Integrator::PathTracer;
+    settings.background = Background::sky();
+    // `spectral` evaluates the films per wavelength instead of per channel.
+    settings.spectral = std::env::args().nth(1).as_deref() == Some("spectral");
+    let fb = Renderer::new(settings).render(&cam, &world);
+    fb.write_ppm(&mut io::stdout().lock()).unwrap();
+
+    eprintln!("\nDone.");
+}
@@ -0,0 +1,59 @@
+use rayt::{
+    camera::Camera,
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::{Conductor, Material},
+    render::{Background, Integrator, RenderSettings, Renderer},
+    vec3::{Color, Point3, Vec3},
+};
+
+use std::io;
+
+/// A golf ball, its dimples bump mapped onto a plain sphere, and a hammered
+/// gold ball on a floor and wall of bevelled tiles from a normal map.
+fn bump_scene() -> HittableList {
+    let mut world = HittableList::default();
+    let tiles = Material::new_normal_mapped(Material::new_lambertian(Color::from([0.6, 0.55, 0.5])), "assets/tiles_normal.png");
+    world.add(Shape::new_quad(Point3::from([-4.0, 0.0, -4.0]), Vec3::from([8.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 8.0]), tiles.clone()));
+    world.add(Shape::new_quad(Point3::from([-4.0, 0.0, -4.0]), Vec3::from([0.0, 8.0, 0.0]), Vec3::from([8.0, 0.0, 0.0]), tiles));
+
+    let golf = Material::new_bump_mapped(Material::new_lambertian(Color::from([0.9, 0.9, 0.9])), "assets/dimples_height.png", 0.01);
+    world.add(Shape::new_sphere(Point3::from([-0.8, 0.7, 0.5]), 0.7, golf));
+    let hammered = Material::new_bump_mapped(Material::new_conductor(Conductor::Gold, 0.05), "assets/dimples_height.png", 0.005);
+    world.add(Shape::new_sphere(Point3::from([1.0, 0.7, -0.5]), 0.7, hammered));
+    world
+}
+
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const IMAGE_WIDTH: u32 = 400;
+const SAMPLES_PER_PIXEL: usize = 64;
+const MAX_DEPTH: usize = 20;
+
+fn main() {
+    let world = bump_scene();
+    let lookfrom = Point3::from([6.0, 3.0, 6.0]);
+    let lookat = Point3::from([0.0, 0.7, 0.0]);
+    let vup = Vec3::from([0.0, 1.0, 0.0]);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        30.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        0.0,
+    );
+
+    let mut settings = RenderSettings::new(IMAGE_WIDTH, ASPECT_RATIO, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    settings.integrator = Integrator::PathTracer;
+    settings.background = Background::sky();
+    let fb = Renderer::new(settings).render(&cam, &world);
+    fb.write_ppm(&mut io::stdout().lock()).unwrap();
+
+    eprintln!("\nDone.");
+}
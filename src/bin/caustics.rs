@@ -0,0 +1,74 @@
+use rayt::{
+    camera::Camera,
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::Material,
+    render::{Background, Integrator, RenderSettings, Renderer},
+    vec3::{Color, Point3, Vec3},
+};
+
+use std::io;
+
+fn small_light() -> Shape {
+    let light = Material::new_diffuse_light(Color::from([60.0, 60.0, 60.0]));
+    Shape::new_quad(Point3::from([-0.5, 6.0, -0.5]), Vec3::from([1.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 1.0]), light)
+}
+
+/// The glass spheres of `one_weekend`, one of them tinted, on a plain floor,
+/// lit only by a small overhead light, so most of the floor is lit through
+/// the glass.
+fn glass_scene() -> HittableList {
+    let mut world = HittableList::default();
+    let ground_material = Material::new_lambertian(Color::from([0.5, 0.5, 0.5]));
+    world.add(Shape::new_quad(Point3::from([-20.0, 0.0, -20.0]), Vec3::from([40.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 40.0]), ground_material));
+
+    world.add(Shape::new_sphere(Point3::from([0.0, 1.0, 0.0]), 1.0, Material::new_dielectric(1.5)));
+    world.add(Shape::new_sphere(Point3::from([-2.5, 0.6, 1.5]), 0.6, Material::new_colored_glass(1.5, Color::from([0.9, 0.5, 0.3]), 1.0)));
+    world.add(Shape::new_sphere(Point3::from([2.0, 0.5, -1.5]), 0.5, Material::new_dielectric(1.5)));
+    world.add(Shape::new_sphere(Point3::from([-3.0, 1.0, -2.0]), 1.0, Material::new_lambertian(Color::from([0.4, 0.2, 0.1]))));
+    world.add(small_light());
+    world
+}
+
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const IMAGE_WIDTH: u32 = 400;
+const MUTATIONS_PER_PIXEL: usize = 256;
+const SAMPLES_PER_PIXEL: usize = 32;
+const PHOTONS: usize = 500_000;
+const MAX_DEPTH: usize = 20;
+
+fn main() {
+    let world = glass_scene();
+    let lookfrom = Point3::from([13.0, 4.0, 3.0]);
+    let lookat = Point3::from([0.0, 0.5, 0.0]);
+    let vup = Vec3::from([0.0, 1.0, 0.0]);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        25.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        0.0,
+    );
+
+    let mut settings = RenderSettings::new(IMAGE_WIDTH, ASPECT_RATIO, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    settings.background = Background::Solid(Color::zero());
+    settings.integrator = match std::env::args().nth(1).as_deref() {
+        Some("photon") => Integrator::PhotonMapping { photons: PHOTONS, gather_radius: 0.1, passes: 8 },
+        Some("path") => Integrator::PathTracer,
+        Some("bdpt") => Integrator::Bidirectional,
+        _ => Integrator::Metropolis { mutations_per_pixel: MUTATIONS_PER_PIXEL, large_step_probability: 0.3 },
+    };
+    let mut renderer = Renderer::new(settings);
+    renderer.lights.add(small_light());
+    let fb = renderer.render(&cam, &world);
+    fb.write_ppm(&mut io::stdout().lock()).unwrap();
+
+    eprintln!("\nDone.");
+}
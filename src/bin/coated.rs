@@ -0,0 +1,70 @@
+use rayt::{
+    camera::Camera,
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::{Coated, Material, Principled},
+    render::{Background, Integrator, RenderSettings, Renderer},
+    texture::Surface,
+    vec3::{Color, Point3, Vec3},
+};
+
+use std::io;
+
+/// Car paint, lacquered plastic and varnished wood: three bases under a
+/// clear, smooth or tinted coat, on a plain floor under the sky.
+fn coated_scene() -> HittableList {
+    let mut world = HittableList::default();
+    let ground_material = Material::new_lambertian(Color::from([0.5, 0.5, 0.5]));
+    world.add(Shape::new_quad(Point3::from([-20.0, 0.0, -20.0]), Vec3::from([40.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 40.0]), ground_material));
+
+    let mut paint = Principled::new(Surface::new_solid_color(Color::from([0.7, 0.05, 0.05])));
+    paint.metallic = Surface::new_constant(0.6);
+    paint.roughness = Surface::new_constant(0.4);
+    let mut car_paint = Coated::new(Material::new_principled(paint));
+    car_paint.roughness = 0.03;
+    world.add(Shape::new_sphere(Point3::from([0.0, 1.0, 0.0]), 1.0, Material::new_coated(car_paint)));
+
+    let plastic = Coated::new(Material::new_lambertian(Color::from([0.1, 0.2, 0.6])));
+    world.add(Shape::new_sphere(Point3::from([-1.8, 0.6, 1.6]), 0.6, Material::new_coated(plastic)));
+
+    let mut varnish = Coated::new(Material::new_noise_lamb(4.0));
+    varnish.roughness = 0.1;
+    varnish.thickness = 0.3;
+    varnish.absorption = Color::from([0.2, 0.8, 2.0]);
+    world.add(Shape::new_sphere(Point3::from([2.0, 0.7, -1.5]), 0.7, Material::new_coated(varnish)));
+    world
+}
+
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const IMAGE_WIDTH: u32 = 400;
+const SAMPLES_PER_PIXEL: usize = 64;
+const MAX_DEPTH: usize = 20;
+
+fn main() {
+    let world = coated_scene();
+    let lookfrom = Point3::from([13.0, 4.0, 3.0]);
+    let lookat = Point3::from([0.0, 0.8, 0.0]);
+    let vup = Vec3::from([0.0, 1.0, 0.0]);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        25.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        0.0,
+    );
+
+    let mut settings = RenderSettings::new(IMAGE_WIDTH, ASPECT_RATIO, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    settings.integrator = Integrator::PathTracer;
+    settings.background = Background::sky();
+    let fb = Renderer::new(settings).render(&cam, &world);
+    fb.write_ppm(&mut io::stdout().lock()).unwrap();
+
+    eprintln!("\nDone.");
+}
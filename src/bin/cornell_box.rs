@@ -0,0 +1,81 @@
+use rayt::{
+    camera::Camera,
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::Material,
+    render::{Background, Integrator, RenderSettings, Renderer},
+    vec3::{Color, Point3, Vec3},
+};
+
+use std::io;
+
+fn add_box(world: &mut HittableList, a: Point3, b: Point3, material: &Material) {
+    let dx = Vec3::from([b.x - a.x, 0.0, 0.0]);
+    let dy = Vec3::from([0.0, b.y - a.y, 0.0]);
+    let dz = Vec3::from([0.0, 0.0, b.z - a.z]);
+    world.add(Shape::new_quad(a, dx, dy, material.clone()));
+    world.add(Shape::new_quad(a + dz, dx, dy, material.clone()));
+    world.add(Shape::new_quad(a, dz, dy, material.clone()));
+    world.add(Shape::new_quad(a + dx, dz, dy, material.clone()));
+    world.add(Shape::new_quad(a, dx, dz, material.clone()));
+    world.add(Shape::new_quad(a + dy, dx, dz, material.clone()));
+}
+
+fn cornell_light() -> Shape {
+    let light = Material::new_diffuse_light(Color::from([15.0, 15.0, 15.0]));
+    Shape::new_quad(Point3::from([343.0, 554.0, 332.0]), Vec3::from([-130.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, -105.0]), light)
+}
+
+fn cornell_box() -> HittableList {
+    let mut world = HittableList::default();
+    let red = Material::new_lambertian(Color::from([0.65, 0.05, 0.05]));
+    let white = Material::new_lambertian(Color::from([0.73, 0.73, 0.73]));
+    let green = Material::new_lambertian(Color::from([0.12, 0.45, 0.15]));
+
+    world.add(Shape::new_quad(Point3::from([555.0, 0.0, 0.0]), Vec3::from([0.0, 555.0, 0.0]), Vec3::from([0.0, 0.0, 555.0]), green));
+    world.add(Shape::new_quad(Point3::from([0.0, 0.0, 0.0]), Vec3::from([0.0, 555.0, 0.0]), Vec3::from([0.0, 0.0, 555.0]), red));
+    world.add(cornell_light());
+    world.add(Shape::new_quad(Point3::from([0.0, 0.0, 0.0]), Vec3::from([555.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 555.0]), white.clone()));
+    world.add(Shape::new_quad(Point3::from([555.0, 555.0, 555.0]), Vec3::from([-555.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, -555.0]), white.clone()));
+    world.add(Shape::new_quad(Point3::from([0.0, 0.0, 555.0]), Vec3::from([555.0, 0.0, 0.0]), Vec3::from([0.0, 555.0, 0.0]), white.clone()));
+
+    add_box(&mut world, Point3::from([130.0, 0.0, 65.0]), Point3::from([295.0, 165.0, 230.0]), &white);
+    add_box(&mut world, Point3::from([265.0, 0.0, 295.0]), Point3::from([430.0, 330.0, 460.0]), &white);
+    world
+}
+
+const ASPECT_RATIO: f64 = 1.0;
+const IMAGE_WIDTH: u32 = 600;
+const SAMPLES_PER_PIXEL: usize = 50;
+const MAX_DEPTH: usize = 50;
+
+fn main() {
+    let world = cornell_box();
+    let lookfrom = Point3::from([278.0, 278.0, -800.0]);
+    let lookat = Point3::from([278.0, 278.0, 0.0]);
+    let vup = Vec3::from([0.0, 1.0, 0.0]);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        40.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        0.0,
+    );
+
+    let mut settings = RenderSettings::new(IMAGE_WIDTH, ASPECT_RATIO, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    settings.background = Background::Solid(Color::zero());
+    settings.integrator = Integrator::PathTracer;
+    let mut renderer = Renderer::new(settings);
+    renderer.lights.add(cornell_light());
+    let fb = renderer.render(&cam, &world);
+    fb.write_ppm(&mut io::stdout().lock()).unwrap();
+
+    eprintln!("\nDone.");
+}
@@ -0,0 +1,72 @@
+use rayt::{
+    camera::Camera,
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::Material,
+    random_double,
+    render::{Background, Integrator, RenderSettings, Renderer},
+    texture::Surface,
+    vec3::{Color, Point3, Vec3},
+};
+
+use std::io;
+
+/// Leaves cut out of textured quads by the alpha of their image, around a
+/// ball seen through a half-transparent gauze.
+fn foliage_scene() -> HittableList {
+    let mut world = HittableList::default();
+    world.add(Shape::new_quad(
+        Point3::from([-6.0, 0.0, -6.0]),
+        Vec3::from([12.0, 0.0, 0.0]),
+        Vec3::from([0.0, 0.0, 12.0]),
+        Material::new_lambertian(Color::from([0.45, 0.4, 0.3])),
+    ));
+    world.add(Shape::new_sphere(Point3::from([0.0, 0.6, -1.0]), 0.6, Material::new_lambertian(Color::from([0.7, 0.15, 0.1]))));
+
+    let leaf = Material::new_alpha_masked(Material::new_image_tex("assets/leaf.png"), Surface::new_image_texture("assets/leaf.png"), Some(0.5));
+    for _ in 0..40 {
+        let q = Point3::from([random_double!(-2.5, 2.5), random_double!(0.0, 2.0), random_double!(-2.0, 1.5)]);
+        let angle = random_double!(0.0, 6.3);
+        let u = 0.6 * Vec3::from([angle.cos(), random_double!(-0.3, 0.3), angle.sin()]);
+        let v = 0.6 * Vec3::from([random_double!(-0.4, 0.4), 1.0, random_double!(-0.4, 0.4)]);
+        world.add(Shape::new_quad(q, u, v, leaf.clone()));
+    }
+
+    let gauze = Material::new_alpha_masked(Material::new_lambertian(Color::from([0.9, 0.9, 0.85])), Surface::new_constant(0.4), None);
+    world.add(Shape::new_quad(Point3::from([-0.3, 0.0, 0.6]), Vec3::from([1.6, 0.0, 0.0]), Vec3::from([0.0, 1.4, 0.0]), gauze));
+    world
+}
+
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const IMAGE_WIDTH: u32 = 400;
+const SAMPLES_PER_PIXEL: usize = 64;
+const MAX_DEPTH: usize = 20;
+
+fn main() {
+    let world = foliage_scene();
+    let lookfrom = Point3::from([3.0, 2.0, 7.0]);
+    let lookat = Point3::from([0.0, 0.8, 0.0]);
+    let vup = Vec3::from([0.0, 1.0, 0.0]);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        40.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        0.0,
+    );
+
+    let mut settings = RenderSettings::new(IMAGE_WIDTH, ASPECT_RATIO, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    settings.integrator = Integrator::PathTracer;
+    settings.background = Background::sky();
+    let fb = Renderer::new(settings).render(&cam, &world);
+    fb.write_ppm(&mut io::stdout().lock()).unwrap();
+
+    eprintln!("\nDone.");
+}
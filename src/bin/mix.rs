@@ -0,0 +1,62 @@
+use rayt::{
+    camera::Camera,
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::{Conductor, Material},
+    render::{Background, Integrator, RenderSettings, Renderer},
+    texture::Surface,
+    vec3::{Color, Point3, Vec3},
+};
+
+use std::io;
+
+/// Rust streaking a copper ball and evenly dulling a steel one, on a floor
+/// of dirt over pale stone; the streaks and patches follow marbled noise.
+fn mix_scene() -> HittableList {
+    let mut world = HittableList::default();
+    let stone = Material::new_lambertian(Color::from([0.75, 0.72, 0.65]));
+    let dirt = Material::new_lambertian(Color::from([0.25, 0.17, 0.1]));
+    let floor = Material::new_mix_tex(stone, dirt, Surface::new_noise_texture(1.5));
+    world.add(Shape::new_quad(Point3::from([-6.0, 0.0, -6.0]), Vec3::from([12.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 12.0]), floor));
+
+    let rust = Material::new_lambertian(Color::from([0.45, 0.16, 0.06]));
+    let copper = Material::new_mix_tex(Material::new_conductor(Conductor::Copper, 0.15), rust.clone(), Surface::new_noise_texture(6.0));
+    world.add(Shape::new_sphere(Point3::from([-0.8, 0.7, 0.5]), 0.7, copper));
+    let steel = Material::new_rough_conductor(Color::from([2.5, 2.6, 2.8]), Color::from([3.1, 3.0, 2.9]), 0.1);
+    world.add(Shape::new_sphere(Point3::from([1.0, 0.7, -0.5]), 0.7, Material::new_mix(steel, rust, 0.35)));
+    world
+}
+
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const IMAGE_WIDTH: u32 = 400;
+const SAMPLES_PER_PIXEL: usize = 64;
+const MAX_DEPTH: usize = 20;
+
+fn main() {
+    let world = mix_scene();
+    let lookfrom = Point3::from([6.0, 3.0, 6.0]);
+    let lookat = Point3::from([0.0, 0.7, 0.0]);
+    let vup = Vec3::from([0.0, 1.0, 0.0]);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        30.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        0.0,
+    );
+
+    let mut settings = RenderSettings::new(IMAGE_WIDTH, ASPECT_RATIO, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    settings.integrator = Integrator::PathTracer;
+    settings.background = Background::sky();
+    let fb = Renderer::new(settings).render(&cam, &world);
+    fb.write_ppm(&mut io::stdout().lock()).unwrap();
+
+    eprintln!("\nDone.");
+}
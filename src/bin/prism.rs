@@ -1,43 +1,16 @@
 use rayt::{
     camera::Camera,
-    hittable::{HitRecord, Hittable, Shape},
+    hittable::Shape,
     hittable_list::HittableList,
-    material::{Material, Scatter},
-    ray::Ray,
-    utils::{INFINITY, clamp},
-    vec3::{unit_vector, Color, Point3, Vec3},
+    material::{Ior, Material},
+    render::{Background, Integrator, RenderSettings, Renderer},
+    vec3::{Color, Point3, Vec3},
 };
 
-use rayon::prelude::*;
+use std::io;
 //use std::sync::Arc;
 //use image::GenericImageView;
 
-#[macro_use]
-extern crate rayt;
-#[macro_use]
-extern crate itertools;
-
-fn ray_color(r: &Ray, world: &HittableList, depth: usize) -> Color {
-    let mut rec = HitRecord::new(Material::new_lambertian(Color::zero()));
-    if depth <= 0 {
-        return Color::new(0.0, 0.0, 0.0);
-    }
-    if world.hit(&r, 0.001, INFINITY, &mut rec) {
-        let mut scattered = Ray::new(Point3::zero(), Vec3::zero(), 0.0);
-        let mut attenuation = Color::zero();
-        if rec
-            .material
-            .scatter(r, &rec, &mut attenuation, &mut scattered)
-        {
-            return attenuation * ray_color(&scattered, world, depth - 1);
-        }
-        return Color::zero();
-    }
-    let unit_direction = unit_vector(r.direction);
-    //let t = 0.5 * (unit_direction.y + 1.0);
-    let t = 0.8 * (unit_direction.y + 0.25);
-    return (1.0 - t) * Color::ones() + t * Color::new(0.5, 0.7, 1.0);
-}
 
 /*
 fn read_image() -> HittableList {
@@ -67,22 +40,34 @@ fn read_image() -> HittableList {
 }
 */
 
+// The beam meets the prism 28 degrees off its first face, so it leaves the
+// second face near grazing, where the colors fan out the most.
+fn light() -> Shape {
+    let light = Material::new_diffuse_light(Color::from([1500.0, 1500.0, 1500.0]));
+    Shape::new_quad(Point3::from([-5.21, 2.3, -2.4]), Vec3::from([-0.19, 0.0, 0.35]), Vec3::from([0.0, 0.4, 0.0]), light)
+}
+
+/// A dense flint prism lit through a vertical slit, fanning the beam into
+/// a spectrum on the floor.
 fn prism() -> HittableList {
     let mut world = HittableList::default();
-    let ground_material = Material::new_metal(Color::from([0.7, 0.5, 0.4]), 0.2);
-    //let ground_material = Material::new_lambertian(Color::from([0.5, 0.5, 0.5]));
-    /*
-    world.add(Shape::new_sphere(
-        Point3::from([0.0, -1000.0, 0.0]),
-        1000.0,
-        ground_material,
-    ));
-    */
+    let ground_material = Material::new_lambertian(Color::from([0.8, 0.8, 0.8]));
+    //let ground_material = Material::new_metal(Color::from([0.7, 0.5, 0.4]), 0.2);
     world.add(Shape::new_triangle(Point3::from([1000.0,0.0,0.0]),Point3::from([0.0,0.0,-1000.0]),Point3::from([0.0,0.0,1000.0]),ground_material.clone()));
     world.add(Shape::new_triangle(Point3::from([0.0,0.0,-1000.0]),Point3::from([-1000.0,0.0,0.0]),Point3::from([0.0,0.0,1000.0]),ground_material.clone()));
-    //let prism_mat= Material::new_dielectric(2.5);
-    let prism_mat = Material::new_metal(Color::from([0.5, 0.5, 0.5]), 0.2);
-    //let prism_mat = Material::new_lambertian(Color::from([0.9,0.1,0.1]));
+
+    // Screen across the beam with a window 0.2 wide and 0.6 tall.
+    let screen = Material::new_lambertian(Color::from([0.1, 0.1, 0.1]));
+    let across = Vec3::from([-0.47, 0.0, 0.88]);
+    let window = Point3::from([-2.65, 1.0, -0.81]) - 0.1 * across;
+    let up = Vec3::from([0.0, 1.0, 0.0]);
+    world.add(Shape::new_quad(window + 0.2 * across - up, 5.0 * across, 4.0 * up, screen.clone()));
+    world.add(Shape::new_quad(window - 5.0 * across - up, 5.0 * across, 4.0 * up, screen.clone()));
+    world.add(Shape::new_quad(window - up, 0.2 * across, up, screen.clone()));
+    world.add(Shape::new_quad(window + 0.6 * up, 0.2 * across, 2.4 * up, screen));
+
+    let prism_mat = Material::new_dispersive_dielectric(Ior::SF11);
+    //let prism_mat = Material::new_metal(Color::from([0.5, 0.5, 0.5]), 0.2);
     let p1 = Point3::from([2.0,0.0,0.0]);
     let p2 = Point3::from([2.0,2.0,0.0]);
     let p3 = Point3::from([0.0,2.0,0.0]);
@@ -97,52 +82,20 @@ fn prism() -> HittableList {
     world.add(Shape::new_triangle(p4,p5,p6,prism_mat.clone()));
     world.add(Shape::new_triangle(p1,p6,p5,prism_mat.clone()));
     world.add(Shape::new_triangle(p1,p2,p6,prism_mat.clone()));
+    world.add(light());
     world
 }
 
-fn render(cam: &Camera, world: &HittableList) -> Vec<Pixel> {
-    let pix_coord: Vec<(u32,u32)> = iproduct!((0..IMAGE_HEIGHT).rev(), 0..IMAGE_WIDTH).collect();
-    let img: Vec<Pixel> = pix_coord.par_iter().map(|(row, col)| simu(*row, *col, cam, world)).collect();
-    img
-}
-
-fn simu(row: u32, col: u32, cam: &Camera, world: &HittableList) -> Pixel {
-    let pixel_color = (1..=SAMPLES_PER_PIXEL)
-        .map(|_| {
-            let u = (col as f64 + random_double!()) / (IMAGE_WIDTH - 1) as f64;
-            let v = (row as f64 + random_double!()) / ( IMAGE_HEIGHT - 1) as f64;
-            ray_color(&cam.get_ray(u, v), world, MAX_DEPTH)
-        })
-        .fold(Color::default(), |sum, c| sum + c);
-    //write_color(pixel_color, 20);
-    let scale = 1.0 / 20.0 as f64;
-    let get_color = |c| (255.999 * clamp(f64::sqrt(scale * c), 0.0, 0.999)) as u32;
-    let r = get_color(pixel_color.x);
-    let g = get_color(pixel_color.y);
-    let b = get_color(pixel_color.z);
-    Pixel {r, g, b}
-}
-
-pub struct Pixel {
-    r: u32,
-    g: u32,
-    b: u32,
-}
 const ASPECT_RATIO: f64 = 16.0 / 9.0;
 const IMAGE_WIDTH: u32 = 1200;
-const IMAGE_HEIGHT: u32 = ((IMAGE_WIDTH as f64) / ASPECT_RATIO) as u32;
-const SAMPLES_PER_PIXEL: usize = 20;
+const SAMPLES_PER_PIXEL: usize = 64;
 const MAX_DEPTH: usize = 50;
 
 fn main() {
-    println!("P3");
-    println!("{} {}", IMAGE_WIDTH, IMAGE_HEIGHT);
-    println!("255");
-
     //let world = read_image();
     let world = prism();
-    let lookfrom = Point3::from([8.0, 2.5, -5.0]);
-    let lookat = Point3::from([0.0,0.5,0.0]);
+    let lookfrom = Point3::from([10.0, 5.0, 5.0]);
+    let lookat = Point3::from([3.5, 0.3, -0.5]);
     let vup = Vec3::from([0.0, 1.0, 0.0]);
     let dist_to_focus = 10.0;
     let aperture = 0.1;
@@ -151,7 +104,7 @@ fn main() {
         lookfrom,
         lookat,
         vup,
-        20.0,
+        35.0,
         ASPECT_RATIO,
         aperture,
         dist_to_focus,
@@ -159,27 +112,15 @@ fn main() {
         0.0,
     );
 
-    let img: Vec<Pixel> = render(&cam, &world);
-    img.iter().for_each(|it| {
-        println!("{} {} {}", it.r, it.g, it.b);
-    });
-    //println!("{}", img.len());
-    /*
-    for j in (0..IMAGE_HEIGHT).rev() {
-        eprint!("\rScanlines remaining: {} ", j);
-
-        for i in 0..IMAGE_WIDTH {
-            let pixel_color = (1..=SAMPLES_PER_PIXEL)
-                .map(|_| {
-                    let u = (i as f64 + random_double!()) / (IMAGE_WIDTH - 1) as f64;
-                    let v = (j as f64 + random_double!()) / (IMAGE_HEIGHT - 1) as f64;
-                    ray_color(&cam.get_ray(u, v), &world, MAX_DEPTH)
-                })
-                .fold(Color::default(), |sum, c| sum + c);
-            write_color(pixel_color, SAMPLES_PER_PIXEL);
-        }
-    }
-    */
+    let mut settings = RenderSettings::new(IMAGE_WIDTH, ASPECT_RATIO, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    settings.background = Background::Solid(Color::from([0.03, 0.03, 0.03]));
+    // The spectrum is lit through specular refraction only, which light
+    // tracing reaches directly.
+    settings.integrator = Integrator::Bidirectional;
+    let mut renderer = Renderer::new(settings);
+    renderer.lights.add(light());
+    let fb = renderer.render(&cam, &world);
+    fb.write_ppm(&mut io::stdout().lock()).unwrap();
 
     eprintln!("\nDone.");
 }
\ No newline at end of file
@@ -0,0 +1,103 @@
+use crate::{
+    hittable::HitRecord,
+    path_sample,
+    utils::PI,
+    vec3::{Color, Vec3},
+};
+use std::ops::BitOr;
+
+/// Lobe a BSDF sample was drawn from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BsdfFlags(u8);
+
+impl BsdfFlags {
+    pub const REFLECTION: Self = Self(1);
+    pub const TRANSMISSION: Self = Self(2);
+    pub const DIFFUSE: Self = Self(4);
+    pub const GLOSSY: Self = Self(8);
+    /// A delta lobe: `eval` and `pdf` are zero for every direction pair.
+    pub const SPECULAR: Self = Self(16);
+    /// The sample depends on the wavelength in `rec.channels`; the first
+    /// such bounce ties the path to that wavelength.
+    pub const DISPERSIVE: Self = Self(32);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_specular(self) -> bool {
+        self.contains(Self::SPECULAR)
+    }
+
+    pub fn is_transmission(self) -> bool {
+        self.contains(Self::TRANSMISSION)
+    }
+}
+
+impl BitOr for BsdfFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A direction drawn by `Bsdf::sample`.
+pub struct BsdfSample {
+    pub wi: Vec3,
+    /// `eval(wo, wi) * |cos(wi)| / pdf`, or the lobe's reflectance for
+    /// specular samples.
+    pub weight: Color,
+    /// Solid-angle density of `wi`, or for specular samples the discrete
+    /// probability of having picked this lobe.
+    pub pdf: f64,
+    pub flags: BsdfFlags,
+}
+
+/// Scattering function of a surface point.
+///
+/// Directions are unit vectors pointing away from `rec.p`: `wo` toward the
+/// viewer, `wi` toward the light. `rec.normal` faces `wo`.
+pub trait Bsdf {
+    /// BSDF value, without the cosine factor.
+    fn eval(&self, _rec: &HitRecord, _wo: Vec3, _wi: Vec3) -> Color {
+        Color::zero()
+    }
+
+    /// Draw `wi` given `wo` from three uniform numbers; the first picks a lobe
+    /// where there is a choice. `None` when the surface absorbs the ray.
+    fn sample(&self, _rec: &HitRecord, _wo: Vec3, _u: [f64; 3]) -> Option<BsdfSample> {
+        None
+    }
+
+    /// Solid-angle density of `sample` returning `wi`; swap the arguments
+    /// for the reverse direction. Zero for specular lobes.
+    fn pdf(&self, _rec: &HitRecord, _wo: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+}
+
+/// Fresh uniform numbers for `Bsdf::sample`.
+pub fn sample_uniforms() -> [f64; 3] {
+    [path_sample!(), path_sample!(), path_sample!()]
+}
+
+/// Cosine-weighted direction about +z from two uniform numbers.
+pub fn cosine_direction(u1: f64, u2: f64) -> Vec3 {
+    let phi = 2.0 * PI * u1;
+    let r = u2.sqrt();
+    Vec3::from([r * phi.cos(), r * phi.sin(), (1.0 - u2).sqrt()])
+}
+
+/// Uniformly distributed unit vector from two uniform numbers.
+pub fn sphere_direction(u1: f64, u2: f64) -> Vec3 {
+    let z = 1.0 - 2.0 * u1;
+    let phi = 2.0 * PI * u2;
+    let s = (1.0 - z * z).max(0.0).sqrt();
+    Vec3::from([s * phi.cos(), s * phi.sin(), z])
+}
+
+/// Point in the unit ball from three uniform numbers.
+pub fn ball_point(u: [f64; 3]) -> Vec3 {
+    u[0].cbrt() * sphere_direction(u[1], u[2])
+}
@@ -5,12 +5,14 @@ use crate::{
 };
 use rand::Rng;
 use std::cmp::Ordering;
+
+const SAH_BINS: usize = 12;
 //use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct BvhNode {
-    left: Box<Shape>,
-    right: Box<Shape>,
+    /// None for a tree over no objects, which no ray hits.
+    children: Option<(Box<Shape>, Box<Shape>)>,
     bbox: Aabb
 }
 
@@ -22,10 +24,10 @@ fn box_x_compare (a: &Shape, b: &Shape) -> Ordering {
     };
     if box_a.min().x < box_b.min().x {
         return Ordering::Less
-    } else if box_a.min().y > box_b.min().y {
+    } else if box_a.min().x > box_b.min().x {
         return Ordering::Greater
     }
-    return Ordering::Equal
+    Ordering::Equal
 }
 
 fn box_y_compare (a: &Shape, b: &Shape) -> Ordering {
@@ -39,7 +41,7 @@ fn box_y_compare (a: &Shape, b: &Shape) -> Ordering {
     } else if box_a.min().y > box_b.min().y {
         return Ordering::Greater
     }
-    return Ordering::Equal
+    Ordering::Equal
 }
 
 fn box_z_compare (a: &Shape, b: &Shape) -> Ordering {
@@ -48,19 +50,23 @@ fn box_z_compare (a: &Shape, b: &Shape) -> Ordering {
     if !a.bounding_box(0.0,0.0, &mut box_a) || !b.bounding_box(0.0,0.0, &mut box_b) {
         eprintln!("No bounding box in bvh_node constructor.\n");
     };
-    if box_a.min().x < box_b.min().x {
+    if box_a.min().z < box_b.min().z {
         return Ordering::Less
-    } else if box_a.min().y > box_b.min().y {
+    } else if box_a.min().z > box_b.min().z {
         return Ordering::Greater
     }
-    return Ordering::Equal
+    Ordering::Equal
 }
 
 impl BvhNode {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(objects: &mut Vec<Shape>, start: usize, end: usize) -> Shape {
         let mut rng = rand::thread_rng();
         let axis: usize = rng.gen_range(0,3);
         let comparator = if axis==0 {box_x_compare} else if axis==1 {box_y_compare} else {box_z_compare};
+        if end <= start {
+            return Self::empty()
+        }
         let object_span = end - start;
 
         let (left, right) = match object_span {
@@ -87,20 +93,154 @@ impl BvhNode {
         }
         let bbox = surrounding_box(&box_left, &box_right);
         Shape::BvhNode(Box::from(BvhNode{
-            left,
-            right,
+            children: Some((left, right)),
+            bbox
+        }))
+    }
+
+    /// Build with the surface area heuristic, binning primitive centroids
+    /// along the longest axis. Deterministic for a given input order.
+    pub fn new_sah(objects: &[Shape]) -> Shape {
+        if objects.is_empty() {
+            return Self::empty()
+        }
+        let boxes = primitive_boxes(objects);
+        let mut order: Vec<usize> = (0..objects.len()).collect();
+        let (left, right) = match objects.len() {
+            1 => (Box::from(objects[0].clone()), Box::from(objects[0].clone())),
+            _ => Self::sah_children(objects, &boxes, &mut order)
+        };
+        Self::from_children(left, right, &boxes, &order)
+    }
+
+    fn sah_build(objects: &[Shape], boxes: &[Aabb], order: &mut [usize]) -> Shape {
+        if order.len() == 1 {
+            return objects[order[0]].clone()
+        }
+        let (left, right) = Self::sah_children(objects, boxes, order);
+        Self::from_children(left, right, boxes, order)
+    }
+
+    fn from_children(left: Box<Shape>, right: Box<Shape>, boxes: &[Aabb], order: &[usize]) -> Shape {
+        let bbox = bounds_of(boxes, order);
+        Shape::BvhNode(Box::from(BvhNode{
+            children: Some((left, right)),
             bbox
         }))
     }
+
+    // Tree over no objects, so that an empty scene builds like it does with
+    // the other accelerators.
+    fn empty() -> Shape {
+        Shape::BvhNode(Box::from(BvhNode{
+            children: None,
+            bbox: Aabb::default()
+        }))
+    }
+
+    fn sah_children(objects: &[Shape], boxes: &[Aabb], order: &mut [usize]) -> (Box<Shape>, Box<Shape>) {
+        let (mid, _) = sah_split(boxes, order);
+        let (l, r) = order.split_at_mut(mid);
+        (Box::new(Self::sah_build(objects, boxes, l)), Box::new(Self::sah_build(objects, boxes, r)))
+    }
+}
+
+pub(crate) fn primitive_boxes(objects: &[Shape]) -> Vec<Aabb> {
+    objects.iter().map(|o| {
+        let mut b = Aabb::default();
+        if !o.bounding_box(0.0, 0.0, &mut b) {
+            eprintln!("No bounding box in bvh_node constructor.\n");
+        }
+        b
+    }).collect()
+}
+
+pub(crate) fn bounds_of(boxes: &[Aabb], order: &[usize]) -> Aabb {
+    order[1..].iter().fold(boxes[order[0]], |b, &i| surrounding_box(&b, &boxes[i]))
+}
+
+/// Sort `order` along the chosen axis and return the split index and axis.
+pub(crate) fn sah_split(boxes: &[Aabb], order: &mut [usize]) -> (usize, usize) {
+    let n = order.len();
+    let first = boxes[order[0]].centroid();
+    let centroids = order[1..].iter().fold(Aabb::new(first, first), |b, &i| {
+        let c = boxes[i].centroid();
+        surrounding_box(&b, &Aabb::new(c, c))
+    });
+    let axis = centroids.longest_axis() as usize;
+    order.sort_by(|&a, &b| {
+        boxes[a].centroid()[axis].partial_cmp(&boxes[b].centroid()[axis]).unwrap_or(Ordering::Equal)
+    });
+
+    let lo = centroids.min()[axis];
+    let extent = centroids.max()[axis] - lo;
+    if n <= 2 || extent <= 0.0 {
+        return (n / 2, axis)
+    }
+
+    let bin_of = |i: usize| {
+        let b = ((boxes[i].centroid()[axis] - lo) / extent * SAH_BINS as f64) as usize;
+        b.min(SAH_BINS - 1)
+    };
+    let mut counts = [0usize; SAH_BINS];
+    let mut bounds: [Option<Aabb>; SAH_BINS] = [None; SAH_BINS];
+    for &i in order.iter() {
+        let b = bin_of(i);
+        counts[b] += 1;
+        bounds[b] = Some(match bounds[b] {
+            Some(bb) => surrounding_box(&bb, &boxes[i]),
+            None => boxes[i],
+        });
+    }
+
+    // Sweep from the right to get the area and count of every right side.
+    let mut right_area = [0.0; SAH_BINS];
+    let mut right_count = [0usize; SAH_BINS];
+    let mut acc: Option<Aabb> = None;
+    let mut cnt = 0;
+    for b in (1..SAH_BINS).rev() {
+        acc = merge(acc, bounds[b]);
+        cnt += counts[b];
+        right_area[b] = acc.map_or(0.0, |bb| bb.area());
+        right_count[b] = cnt;
+    }
+
+    let mut best_cost = f64::INFINITY;
+    let mut best_split = n / 2;
+    let mut acc: Option<Aabb> = None;
+    let mut left_count = 0;
+    for b in 0..SAH_BINS - 1 {
+        acc = merge(acc, bounds[b]);
+        left_count += counts[b];
+        if left_count == 0 || right_count[b + 1] == 0 {
+            continue
+        }
+        let cost = acc.map_or(0.0, |bb| bb.area()) * left_count as f64
+            + right_area[b + 1] * right_count[b + 1] as f64;
+        if cost < best_cost {
+            best_cost = cost;
+            best_split = left_count;
+        }
+    }
+    (best_split, axis)
+}
+
+fn merge(a: Option<Aabb>, b: Option<Aabb>) -> Option<Aabb> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(surrounding_box(&a, &b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
 }
 
 
 impl Hittable for BvhNode {
     fn hit(&self, r:&Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let Some((left, right)) = &self.children else {return false};
         if !self.bbox.hit(r, t_min, t_max) {return false}
 
-        let hit_left = self.left.hit(r, t_min, t_max, rec);
-        let hit_right = self.right.hit(r, t_min, if hit_left {rec.t} else {t_max}, rec);
+        let hit_left = left.hit(r, t_min, t_max, rec);
+        let hit_right = right.hit(r, t_min, if hit_left {rec.t} else {t_max}, rec);
 
         hit_left || hit_right
 
@@ -108,7 +248,8 @@ impl Hittable for BvhNode {
 
 
     fn bounding_box(&self, _time0: f64, _time1:f64, output_box: &mut Aabb) -> bool {
-        *output_box = self.bbox.clone();
+        if self.children.is_none() {return false}
+        *output_box = self.bbox;
         true
     }
 }
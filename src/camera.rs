@@ -1,8 +1,8 @@
 use crate::{
-    random_double,
+    path_sample,
     ray::Ray,
-    utils::degrees_to_radians,
-    vec3::{cross, random_in_unit_disk, unit_vector, Point3, Vec3},
+    utils::{degrees_to_radians, PI},
+    vec3::{cross, dot, unit_vector, Point3, Vec3},
 };
 
 pub struct Camera {
@@ -12,13 +12,14 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
-    _w: Vec3,
+    w: Vec3,
     lens_radius: f64,
     time0: f64,
     time1: f64,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Point3,
         lookat: Point3,
@@ -52,20 +53,79 @@ impl Camera {
             vertical,
             u,
             v,
-            _w: w,
+            w,
             lens_radius,
             time0,
             time1,
         }
     }
 
+    /// Ray through film coordinates `(s, t)`, with a random point on the
+    /// lens and shutter time.
     pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
+        // Uniform point on the lens.
+        let (r, phi) = (path_sample!().sqrt(), 2.0 * PI * path_sample!());
+        let rd = self.lens_radius * r * Vec3::from([phi.cos(), phi.sin(), 0.0]);
         let offset = self.u * rd.x + self.v * rd.y;
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-            random_double!(self.time0, self.time1),
+            self.time0 + (self.time1 - self.time0) * path_sample!(),
         )
     }
+
+    /// Ray through film coordinates `(s, t)` from the lens center, ignoring
+    /// defocus blur and shutter time.
+    pub fn get_pinhole_ray(&self, s: f64, t: f64) -> Ray {
+        Ray::new(
+            self.origin,
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin,
+            self.time0,
+        )
+    }
+
+    pub fn origin(&self) -> Point3 {
+        self.origin
+    }
+
+    /// Viewing direction, the normal of the film.
+    pub fn forward(&self) -> Vec3 {
+        -self.w
+    }
+
+    // Film area scaled to unit distance from the lens.
+    fn film_area(&self) -> f64 {
+        let d = dot(&(self.lower_left_corner - self.origin), &self.forward());
+        self.horizontal.length() * self.vertical.length() / (d * d)
+    }
+
+    /// Film coordinates `(s, t)` hit by a pinhole ray along `direction` and
+    /// its importance `We`, normalised to integrate to one over the film.
+    pub fn importance(&self, direction: Vec3) -> Option<(f64, f64, f64)> {
+        let wd = unit_vector(direction);
+        let cosine = dot(&wd, &self.forward());
+        if cosine <= 0.0 {
+            return None
+        }
+        let d = dot(&(self.lower_left_corner - self.origin), &self.forward());
+        let rel = self.origin + wd * (d / cosine) - self.lower_left_corner;
+        let s = dot(&rel, &self.horizontal) / self.horizontal.length_squared();
+        let t = dot(&rel, &self.vertical) / self.vertical.length_squared();
+        if !(0.0..1.0).contains(&s) || !(0.0..1.0).contains(&t) {
+            return None
+        }
+        Some((s, t, 1.0 / (self.film_area() * cosine.powi(4))))
+    }
+
+    /// Solid-angle density of `get_pinhole_ray` leaving along `direction`
+    /// for uniformly sampled film coordinates.
+    pub fn pdf_direction(&self, direction: Vec3) -> f64 {
+        match self.importance(direction) {
+            Some(_) => {
+                let cosine = dot(&unit_vector(direction), &self.forward());
+                1.0 / (self.film_area() * cosine.powi(3))
+            }
+            None => 0.0,
+        }
+    }
 }
@@ -5,21 +5,22 @@ use crate::{
     vec3::{Vec3, Point3},
     aabb::Aabb
 };
+use std::sync::Arc;
 use std::f64::consts::PI;
 
 #[derive(Clone)]
 pub struct Cylinder {
     r: f64,
     d: f64,
-    material: Material,
+    material: Arc<Material>,
 }
 
 impl Cylinder {
-    pub fn new(r: f64, d: f64, material: Material) -> Self {
+    pub fn new(r: f64, d: f64, material: impl Into<Arc<Material>>) -> Self {
         Self {
             r,
             d,
-            material
+            material: material.into()
         }
     }
 
@@ -50,26 +51,49 @@ impl Hittable for Cylinder {
             if (tmp1<t_min)||(tmp1>t_max) {
                 return false
             }
-            rec.t = (-hb-root)/a;
-            rec.p = r.at(rec.t);
-            let outward_normal = Vec3::from([rec.p.x, 0.0, rec.p.z]) / self.r;
+            let t = (-hb-root)/a;
+            let p = r.at(t);
+            let outward_normal = Vec3::from([p.x, 0.0, p.z]) / self.r;
+            // Around the axis like a sphere, and up the side.
+            let (mut u, mut v) = (0.0, 0.0);
+            Cylinder::get_sphere_uv(&outward_normal, &mut u, &mut v);
+            v = p.y / self.d;
+            if !self.material.opaque(u, v, &p) {
+                return false
+            }
+            rec.t = t;
+            rec.p = p;
             rec.set_face_normal(r, &outward_normal);
-            Cylinder::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
-            rec.material = self.material.clone();
-            return true;
+            rec.u = u;
+            rec.v = v;
+            rec.dpdu = 2.0 * PI * self.r * Vec3::from([outward_normal.z, 0.0, -outward_normal.x]);
+            rec.dpdv = Vec3::from([0.0, self.d, 0.0]);
+            rec.set_material(&self.material);
+            true
         } else {
-            rec.t = (self.d - r.origin.y)/r.direction.y;
-            rec.p = r.at(rec.t);
-            let outward_normal = Vec3::from([0.0,1.0,0.0]);
-            Cylinder::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
-            rec.material = self.material.clone();
-            return true;
+            let t = (self.d - r.origin.y)/r.direction.y;
+            let p = r.at(t);
+            // The cap is mapped from above, with `v` running toward -z.
+            let u = 0.5 * (p.x / self.r + 1.0);
+            let v = 0.5 * (1.0 - p.z / self.r);
+            if !self.material.opaque(u, v, &p) {
+                return false
+            }
+            rec.t = t;
+            rec.p = p;
+            rec.set_face_normal(r, &Vec3::from([0.0,1.0,0.0]));
+            rec.u = u;
+            rec.v = v;
+            rec.dpdu = Vec3::from([2.0 * self.r, 0.0, 0.0]);
+            rec.dpdv = Vec3::from([0.0, 0.0, -2.0 * self.r]);
+            rec.set_material(&self.material);
+            true
 
         }
 
     }
     fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
-        output_box.modify(Point3::from([(-1.0)*self.r, 0.0, (-1.0)*self.r]), Point3::from([self.r, self.d, self.r]));
+        output_box.modify(Point3::from([-self.r, 0.0, -self.r]), Point3::from([self.r, self.d, self.r]));
         true
     }
 }
@@ -1,41 +1,68 @@
 use crate::{
-    material::Material,
+    material::{Material, Scatter},
     ray::Ray,
-    vec3::{dot, Point3, Vec3},
+    spectrum::Channels,
+    vec3::{dot, Color, Point3, Vec3},
     sphere::Sphere,
     sphere_blur::SphereBlur,
     triangle::Triangle,
     mesh::Mesh,
     cylinder::Cylinder,
+    quad::Quad,
     aabb::Aabb,
     bvh::BvhNode
 };
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
-    pub material: Material,
+    /// Material at the hit, shared with the shape it belongs to; None
+    /// until a hit is recorded.
+    pub material: Option<Arc<Material>>,
     pub t: f64,
+    /// Length of the ray up to the hit, `t` scaled by the direction's length.
+    pub distance: f64,
     pub u: f64,
     pub v: f64,
+    /// Derivatives of `p` along `u` and `v`, zero where the surface has
+    /// no tangents.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub front_face: bool,
+    /// Channels of the ray that found the hit.
+    pub channels: Channels,
+}
+
+impl Default for HitRecord {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HitRecord {
-    pub fn new(material: Material) -> Self {
+    pub fn new() -> Self {
         Self {
             p: Point3::zero(),
             normal: Point3::zero(),
-            material,
+            material: None,
             t: 0.0,
+            distance: 0.0,
             u: 0.0,
             v: 0.0,
+            dpdu: Vec3::zero(),
+            dpdv: Vec3::zero(),
             front_face: false,
+            channels: Channels::Rgb,
         }
     }
 
+    /// Record which side `r` hit, how far it travelled and the channels it
+    /// carries. `t` must be set first.
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
+        self.channels = r.channels;
+        self.distance = self.t * r.direction.length();
         self.front_face = dot(&r.direction, outward_normal) < 0.0;
         self.normal = if self.front_face {
             *outward_normal
@@ -43,13 +70,67 @@ impl HitRecord {
             -*outward_normal
         };
     }
+
+    /// Take on `material`, letting its normal map, if any, bend the normal.
+    /// The normal, `(u, v)` and tangents must be set first.
+    pub fn set_material(&mut self, material: &Arc<Material>) {
+        match material.as_ref() {
+            Material::NormalMapped(m) => {
+                let outward = if self.front_face { self.normal } else { -self.normal };
+                let n = m.map.shading_normal(outward, self.dpdu, self.dpdv, self.u, self.v, &self.p);
+                self.normal = if self.front_face { n } else { -n };
+                self.set_material(&m.base);
+            }
+            Material::AlphaMasked(m) => self.set_material(&m.base),
+            _ => self.material = Some(Arc::clone(material)),
+        }
+    }
+
+    /// Material at the hit. Panics before a hit is recorded.
+    pub fn material(&self) -> &Material {
+        self.material.as_deref().expect("no material before a hit")
+    }
+
+    /// RGB color `c` in the units of the ray that found the hit.
+    pub fn uplift(&self, c: Color) -> Color {
+        self.channels.uplift(c)
+    }
+
+    /// Light emitted by the surface at the hit.
+    pub fn emitted(&self) -> Color {
+        self.uplift(self.material().emitted(self.u, self.v, &self.p))
+    }
 }
 
 pub trait Hittable {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
     fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool;
+
+    /// Solid-angle density of `random` toward `direction` as seen from `origin`.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// A direction from `origin` toward a random point on the surface.
+    fn random(&self, _origin: Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// Surface area, or zero for shapes that cannot be sampled as emitters.
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    /// A uniformly distributed point on the surface and the outward normal there.
+    fn random_point(&self) -> (Point3, Vec3) {
+        (Point3::zero(), Vec3::new(0.0, 1.0, 0.0))
+    }
 }
 
+/// A primitive or acceleration structure. Primitives hold their material
+/// by `Arc`: pass clones of one `Arc<Material>` to share a material, and
+/// its images, among many shapes such as the faces of a mesh. A `Material`
+/// passed by value gets an `Arc` of its own.
 #[derive(Clone)]
 pub enum Shape {
     Sphere(Box<Sphere>),
@@ -57,23 +138,27 @@ pub enum Shape {
     Triangle(Box<Triangle>),
     Cylinder(Box<Cylinder>),
     Mesh(Box<Mesh>),
+    Quad(Box<Quad>),
     BvhNode(Box<BvhNode>),
 }
 
 impl Shape {
-    pub fn new_sphere(center: Point3, radius: f64, material: Material) -> Self {
+    pub fn new_sphere(center: Point3, radius: f64, material: impl Into<Arc<Material>>) -> Self {
         Shape::Sphere(Box::new(Sphere::new(center, radius, material)))
     }
-    pub fn new_sphere_blur(center1: Point3, center2: Point3, radius: f64, material: Material, time1: f64, time2: f64) -> Self {
+    pub fn new_sphere_blur(center1: Point3, center2: Point3, radius: f64, material: impl Into<Arc<Material>>, time1: f64, time2: f64) -> Self {
         Shape::SphereBlur(Box::new(SphereBlur::new(center1, center2, radius, material, time1, time2)))
     }
-    pub fn new_triangle(a0: Point3, a1: Point3, a2: Point3, material: Material) -> Self {
+    pub fn new_triangle(a0: Point3, a1: Point3, a2: Point3, material: impl Into<Arc<Material>>) -> Self {
         Shape::Triangle(Box::new(Triangle::new(a0, a1, a2, material)))
     }
-    pub fn new_mesh(a0: Point3, a1: Point3, a2: Point3, n0:Point3, n1:Point3, n2: Point3, material: Material) -> Self {
+    pub fn new_mesh(a0: Point3, a1: Point3, a2: Point3, n0:Point3, n1:Point3, n2: Point3, material: impl Into<Arc<Material>>) -> Self {
         Shape::Mesh(Box::new(Mesh::new(a0, a1, a2, n0, n1, n2, material)))
     }
-    pub fn new_cylinder(r: f64, d: f64, material: Material) -> Self {
+    pub fn new_quad(q: Point3, u: Vec3, v: Vec3, material: impl Into<Arc<Material>>) -> Self {
+        Shape::Quad(Box::new(Quad::new(q, u, v, material)))
+    }
+    pub fn new_cylinder(r: f64, d: f64, material: impl Into<Arc<Material>>) -> Self {
         Shape::Cylinder(Box::new(Cylinder::new(r, d, material)))
     }
 }
@@ -92,6 +177,7 @@ impl Hittable for Shape {
             Shape::Triangle(m) => m.hit(r, t_min, t_max, rec),
             Shape::Cylinder(m) => m.hit(r, t_min, t_max, rec),
             Shape::Mesh(m) => m.hit(r, t_min, t_max, rec),
+            Shape::Quad(m) => m.hit(r, t_min, t_max, rec),
             Shape::BvhNode(m) => m.hit(r, t_min, t_max, rec)
         }
     }
@@ -108,8 +194,45 @@ impl Hittable for Shape {
             Shape::Triangle(m) => m.bounding_box(time0, time1, bounding_box),
             Shape::Cylinder(m) => m.bounding_box(time0, time1, bounding_box),
             Shape::Mesh(m) => m.bounding_box(time0, time1, bounding_box),
+            Shape::Quad(m) => m.bounding_box(time0, time1, bounding_box),
             Shape::BvhNode(m) => m.bounding_box(time0, time1, bounding_box)
         }
     }
 
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        match self {
+            Shape::Sphere(m) => m.pdf_value(origin, direction),
+            Shape::Triangle(m) => m.pdf_value(origin, direction),
+            Shape::Quad(m) => m.pdf_value(origin, direction),
+            _ => 0.0,
+        }
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        match self {
+            Shape::Sphere(m) => m.random(origin),
+            Shape::Triangle(m) => m.random(origin),
+            Shape::Quad(m) => m.random(origin),
+            _ => Vec3::new(1.0, 0.0, 0.0),
+        }
+    }
+
+    fn area(&self) -> f64 {
+        match self {
+            Shape::Sphere(m) => m.area(),
+            Shape::Triangle(m) => m.area(),
+            Shape::Quad(m) => m.area(),
+            _ => 0.0,
+        }
+    }
+
+    fn random_point(&self) -> (Point3, Vec3) {
+        match self {
+            Shape::Sphere(m) => m.random_point(),
+            Shape::Triangle(m) => m.random_point(),
+            Shape::Quad(m) => m.random_point(),
+            _ => (Point3::zero(), Vec3::new(0.0, 1.0, 0.0)),
+        }
+    }
+
 }
@@ -1,6 +1,8 @@
 use crate::{
     hittable::{HitRecord, Hittable, Shape},
+    path_sample,
     ray::Ray,
+    vec3::{Point3, Vec3},
     aabb::{Aabb, surrounding_box}
 };
 //use std::sync::Arc;
@@ -40,11 +42,20 @@ impl Hittable for HittableList {
 
         for object in self.objects.iter() {
             if !object.bounding_box(time0, time1, &mut temp_box) { return false }
-            *output_box = if first_box { temp_box.clone() } else {surrounding_box(output_box, &temp_box)};
+            *output_box = if first_box { temp_box } else {surrounding_box(output_box, &temp_box)};
             first_box = false;
         }
 
         true
     }
 
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects.iter().map(|object| weight * object.pdf_value(origin, direction)).sum()
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        let idx = (path_sample!() * self.objects.len() as f64) as usize;
+        self.objects[idx.min(self.objects.len() - 1)].random(origin)
+    }
 }
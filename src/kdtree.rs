@@ -0,0 +1,263 @@
+use crate::{
+    hittable::{HitRecord, Hittable, Shape},
+    ray::Ray,
+    aabb::Aabb,
+    bvh::{bounds_of, primitive_boxes},
+    vec3::Point3
+};
+use std::cmp::Ordering;
+
+const TRAVERSAL_COST: f64 = 1.0;
+const INTERSECT_COST: f64 = 80.0;
+const EMPTY_BONUS: f64 = 0.5;
+// Splits that cost more than the leaf they replace are tolerated this many
+// times along a path, since a later split may still pay them back.
+const MAX_BAD_REFINES: usize = 3;
+const STACK_SIZE: usize = 64;
+const DEFAULT_LEAF_SIZE: usize = 1;
+
+#[derive(Clone, Copy)]
+struct KdNode {
+    split: f64,
+    /// Leaf: first entry in `indices`. Interior: index of the child above the
+    /// split; the child below always directly follows its parent.
+    offset: u32,
+    /// Number of primitives in a leaf.
+    count: u32,
+    /// Split axis, or 3 for a leaf.
+    axis: u8,
+}
+
+impl KdNode {
+    fn is_leaf(&self) -> bool {
+        self.axis == 3
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Edge {
+    t: f64,
+    start: bool,
+}
+
+fn with_axis(p: Point3, axis: usize, value: f64) -> Point3 {
+    let mut c = [p.x, p.y, p.z];
+    c[axis] = value;
+    Point3::from(c)
+}
+
+/// SAH kd-tree over a list of shapes.
+///
+/// Primitives straddling a split plane are referenced from both sides, so
+/// leaves hold indices into `primitives` rather than the shapes themselves.
+pub struct KdTree {
+    primitives: Vec<Shape>,
+    indices: Vec<u32>,
+    nodes: Vec<KdNode>,
+    bounds: Aabb,
+}
+
+impl KdTree {
+    /// Build with a depth limit of `8 + 1.3 log2(n)` and single-primitive leaves.
+    pub fn new(objects: Vec<Shape>) -> Self {
+        let max_depth = (8.0 + 1.3 * (objects.len().max(1) as f64).log2()).round() as usize;
+        Self::with_limits(objects, max_depth, DEFAULT_LEAF_SIZE)
+    }
+
+    /// Build with an explicit depth limit and the primitive count below which
+    /// a node is never split. `max_depth` is capped by the traversal stack.
+    pub fn with_limits(objects: Vec<Shape>, max_depth: usize, leaf_size: usize) -> Self {
+        let mut tree = Self { primitives: Vec::new(), indices: Vec::new(), nodes: Vec::new(), bounds: Aabb::default() };
+        if objects.is_empty() {
+            return tree
+        }
+        let boxes = primitive_boxes(&objects);
+        let prims: Vec<usize> = (0..objects.len()).collect();
+        tree.bounds = bounds_of(&boxes, &prims);
+        tree.build(&boxes, prims, tree.bounds, max_depth.min(STACK_SIZE - 1), leaf_size.max(1), 0);
+        tree.primitives = objects;
+        tree
+    }
+
+    fn build(&mut self, boxes: &[Aabb], prims: Vec<usize>, bounds: Aabb, depth: usize, leaf_size: usize, bad_refines: usize) {
+        let idx = self.nodes.len();
+        let n = prims.len();
+        if n <= leaf_size || depth == 0 {
+            self.make_leaf(&prims);
+            return
+        }
+
+        let (min, max) = (bounds.min(), bounds.max());
+        let d = max - min;
+        let total_area = bounds.area();
+        let inv_total_area = 1.0 / total_area;
+        let leaf_cost = INTERSECT_COST * n as f64;
+
+        // Sweep the sorted box edges along each axis, longest first, keeping
+        // the cheapest split plane strictly inside the node.
+        let mut best: Option<(usize, f64)> = None;
+        let mut best_cost = f64::INFINITY;
+        let mut edges = Vec::with_capacity(2 * n);
+        let mut axis = bounds.longest_axis() as usize;
+        for _ in 0..3 {
+            edges.clear();
+            for &p in prims.iter() {
+                edges.push(Edge { t: boxes[p].min()[axis], start: true });
+                edges.push(Edge { t: boxes[p].max()[axis], start: false });
+            }
+            edges.sort_by(|a, b| match a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal) {
+                Ordering::Equal => b.start.cmp(&a.start),
+                o => o,
+            });
+
+            let (o0, o1) = ((axis + 1) % 3, (axis + 2) % 3);
+            let cap = 2.0 * d[o0] * d[o1];
+            let side = 2.0 * (d[o0] + d[o1]);
+            let mut below = 0;
+            let mut above = n;
+            for e in edges.iter() {
+                if !e.start {
+                    above -= 1;
+                }
+                if e.t > min[axis] && e.t < max[axis] {
+                    let below_area = cap + (e.t - min[axis]) * side;
+                    let above_area = cap + (max[axis] - e.t) * side;
+                    let p_below = below_area * inv_total_area;
+                    let p_above = above_area * inv_total_area;
+                    let bonus = if below == 0 || above == 0 { EMPTY_BONUS } else { 0.0 };
+                    let cost = TRAVERSAL_COST
+                        + INTERSECT_COST * (1.0 - bonus) * (p_below * below as f64 + p_above * above as f64);
+                    if cost < best_cost {
+                        best_cost = cost;
+                        best = Some((axis, e.t));
+                    }
+                }
+                if e.start {
+                    below += 1;
+                }
+            }
+            if best.is_some() {
+                break
+            }
+            axis = (axis + 1) % 3;
+        }
+
+        let mut bad_refines = bad_refines;
+        if best_cost > leaf_cost {
+            bad_refines += 1;
+        }
+        let (axis, split) = match best {
+            Some(b) if (best_cost <= 4.0 * leaf_cost || n <= 16) && bad_refines <= MAX_BAD_REFINES => b,
+            _ => {
+                self.make_leaf(&prims);
+                return
+            }
+        };
+
+        // Boxes starting before the split go below, boxes ending after it go
+        // above, and straddling ones go to both. Flat boxes lying on the
+        // plane stay below.
+        let mut prims_below = Vec::new();
+        let mut prims_above = Vec::new();
+        for &p in prims.iter() {
+            let (lo, hi) = (boxes[p].min()[axis], boxes[p].max()[axis]);
+            if lo < split || (lo == split && hi == split) {
+                prims_below.push(p);
+            }
+            if hi > split {
+                prims_above.push(p);
+            }
+        }
+        drop(prims);
+
+        self.nodes.push(KdNode { split, offset: 0, count: 0, axis: axis as u8 });
+        self.build(boxes, prims_below, Aabb::new(min, with_axis(max, axis, split)), depth - 1, leaf_size, bad_refines);
+        self.nodes[idx].offset = self.nodes.len() as u32;
+        self.build(boxes, prims_above, Aabb::new(with_axis(min, axis, split), max), depth - 1, leaf_size, bad_refines);
+    }
+
+    fn make_leaf(&mut self, prims: &[usize]) {
+        self.nodes.push(KdNode { split: 0.0, offset: self.indices.len() as u32, count: prims.len() as u32, axis: 3 });
+        self.indices.extend(prims.iter().map(|&p| p as u32));
+    }
+
+    pub fn len(&self) -> usize {
+        self.primitives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+}
+
+impl Hittable for KdTree {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let (mut t0, mut t1) = match self.bounds.hit_range(r, t_min, t_max) {
+            Some(range) if !self.nodes.is_empty() => range,
+            _ => return false,
+        };
+        let inv_dir = Point3::from([1.0 / r.direction.x, 1.0 / r.direction.y, 1.0 / r.direction.z]);
+        let mut stack = [(0usize, 0.0, 0.0); STACK_SIZE];
+        let mut sp = 0;
+        let mut idx = 0;
+        let mut hit_anything = false;
+        let mut closest_so_far = t_max;
+
+        loop {
+            // Nodes are visited front to back, so once a hit lies before the
+            // current segment nothing further along can be closer.
+            if closest_so_far < t0 {
+                break
+            }
+            let node = &self.nodes[idx];
+            if node.is_leaf() {
+                let start = node.offset as usize;
+                for &p in &self.indices[start..start + node.count as usize] {
+                    if self.primitives[p as usize].hit(r, t_min, closest_so_far, rec) {
+                        hit_anything = true;
+                        closest_so_far = rec.t;
+                    }
+                }
+                if sp == 0 {
+                    break
+                }
+                sp -= 1;
+                let (next, next_t0, next_t1) = stack[sp];
+                idx = next;
+                t0 = next_t0;
+                t1 = next_t1;
+                continue
+            }
+
+            let axis = node.axis as usize;
+            let t_plane = (node.split - r.origin[axis]) * inv_dir[axis];
+            let below_first = r.origin[axis] < node.split
+                || (r.origin[axis] == node.split && r.direction[axis] <= 0.0);
+            let (first, second) = if below_first {
+                (idx + 1, node.offset as usize)
+            } else {
+                (node.offset as usize, idx + 1)
+            };
+
+            if t_plane > t1 || t_plane <= 0.0 {
+                idx = first;
+            } else if t_plane < t0 {
+                idx = second;
+            } else {
+                stack[sp] = (second, t_plane, t1);
+                sp += 1;
+                idx = first;
+                t1 = t_plane;
+            }
+        }
+        hit_anything
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        if self.nodes.is_empty() {
+            return false
+        }
+        *output_box = self.bounds;
+        true
+    }
+}
@@ -4,6 +4,10 @@ pub mod color;
 pub mod hittable;
 pub mod hittable_list;
 pub mod material;
+pub mod bsdf;
+pub mod microfacet;
+pub mod spectrum;
+pub mod thin_film;
 pub mod ray;
 pub mod sphere;
 pub mod utils;
@@ -12,8 +16,17 @@ pub mod triangle;
 pub mod mesh;
 pub mod cylinder;
 pub mod bvh;
+pub mod linear_bvh;
+pub mod kdtree;
+pub mod octree;
 pub mod texture;
 pub mod sphere_blur;
 pub mod perlin;
 pub mod onb;
+pub mod path_tracer;
+pub mod mlt;
+pub mod photon_map;
+pub mod bdpt;
 pub mod pdf;
+pub mod quad;
+pub mod render;
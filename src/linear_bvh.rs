@@ -0,0 +1,133 @@
+use crate::{
+    hittable::{HitRecord, Hittable, Shape},
+    ray::Ray,
+    aabb::Aabb,
+    bvh::{bounds_of, primitive_boxes, sah_split}
+};
+
+const MAX_LEAF_SIZE: usize = 4;
+// Past this depth splits fall back to the median, which bounds the tree
+// depth and so the fixed traversal stack.
+const MAX_SAH_DEPTH: usize = 32;
+const STACK_SIZE: usize = 64;
+
+#[derive(Clone, Copy)]
+struct LinearBvhNode {
+    bbox: Aabb,
+    /// Leaf: first primitive. Interior: index of the second child; the first
+    /// child always directly follows its parent.
+    offset: u32,
+    /// Number of primitives in a leaf, zero for interior nodes.
+    count: u16,
+    axis: u8,
+}
+
+/// SAH BVH flattened into a depth-first array.
+///
+/// Primitives are moved, not cloned, into leaf order so each leaf covers a
+/// contiguous `offset..offset + count` range of `primitives`.
+pub struct LinearBvh {
+    primitives: Vec<Shape>,
+    nodes: Vec<LinearBvhNode>,
+}
+
+impl LinearBvh {
+    pub fn new(objects: Vec<Shape>) -> Self {
+        if objects.is_empty() {
+            return Self { primitives: objects, nodes: Vec::new() }
+        }
+        let boxes = primitive_boxes(&objects);
+        let mut order: Vec<usize> = (0..objects.len()).collect();
+        let mut nodes = Vec::with_capacity(2 * objects.len() / MAX_LEAF_SIZE + 1);
+        Self::build(&boxes, &mut order, 0, 0, &mut nodes);
+
+        let mut slots: Vec<Option<Shape>> = objects.into_iter().map(Some).collect();
+        let primitives = order.iter().map(|&i| slots[i].take().unwrap()).collect();
+        Self { primitives, nodes }
+    }
+
+    fn build(boxes: &[Aabb], order: &mut [usize], first: usize, depth: usize, nodes: &mut Vec<LinearBvhNode>) {
+        let n = order.len();
+        let bbox = bounds_of(boxes, order);
+        let idx = nodes.len();
+        nodes.push(LinearBvhNode { bbox, offset: first as u32, count: n as u16, axis: 0 });
+        if n <= MAX_LEAF_SIZE {
+            return
+        }
+
+        let (mut mid, axis) = sah_split(boxes, order);
+        if depth >= MAX_SAH_DEPTH {
+            mid = n / 2;
+        }
+        let (l, r) = order.split_at_mut(mid);
+        Self::build(boxes, l, first, depth + 1, nodes);
+        let second = nodes.len();
+        Self::build(boxes, r, first + mid, depth + 1, nodes);
+        nodes[idx] = LinearBvhNode { bbox, offset: second as u32, count: 0, axis: axis as u8 };
+    }
+
+    pub fn len(&self) -> usize {
+        self.primitives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+}
+
+impl Hittable for LinearBvh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        if self.nodes.is_empty() {
+            return false
+        }
+        let dir_is_neg = [r.direction.x < 0.0, r.direction.y < 0.0, r.direction.z < 0.0];
+        let mut stack = [0usize; STACK_SIZE];
+        let mut sp = 0;
+        let mut idx = 0;
+        let mut hit_anything = false;
+        let mut closest_so_far = t_max;
+
+        loop {
+            let node = &self.nodes[idx];
+            if node.bbox.hit(r, t_min, closest_so_far) {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    for object in &self.primitives[start..start + node.count as usize] {
+                        if object.hit(r, t_min, closest_so_far, rec) {
+                            hit_anything = true;
+                            closest_so_far = rec.t;
+                        }
+                    }
+                } else {
+                    // Visit the child nearer the ray origin first so the far
+                    // one can be culled by the closer hit.
+                    let (near, far) = if dir_is_neg[node.axis as usize] {
+                        (node.offset as usize, idx + 1)
+                    } else {
+                        (idx + 1, node.offset as usize)
+                    };
+                    stack[sp] = far;
+                    sp += 1;
+                    idx = near;
+                    continue;
+                }
+            }
+            if sp == 0 {
+                break;
+            }
+            sp -= 1;
+            idx = stack[sp];
+        }
+        hit_anything
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        match self.nodes.first() {
+            Some(node) => {
+                *output_box = node.bbox;
+                true
+            }
+            None => false,
+        }
+    }
+}
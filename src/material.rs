@@ -1,20 +1,65 @@
 use crate::{
+    bsdf::{ball_point, cosine_direction, sample_uniforms, sphere_direction, Bsdf, BsdfFlags, BsdfSample},
     hittable::HitRecord,
-    random_double,
-    ray::Ray,
-    texture::Surface,
-    vec3::{dot, random_in_unit_sphere, random_unit_vector, reflect, refract, unit_vector, Color},
+    microfacet::{fresnel_conductor, fresnel_dielectric, fresnel_schlick, refraction_half_vector, Ggx, Gtr1},
+    onb::ONB,
+    path_sample,
+    ray::{MediumWalk, Ray},
+    texture::{NormalMap, Surface},
+    thin_film::{Substrate, ThinFilm},
+    utils::{clamp, PI},
+    vec3::{dot, reflect, refract, unit_vector, Color, Point3, Vec3},
 };
 use crate::texture::Texture;
+use std::sync::Arc;
 
-pub trait Scatter {
+/// Surface response used by the integrators: a `Bsdf` plus emission.
+///
+/// `scatter` and `scattering_pdf` are the one-step interface of the older
+/// integrators, built on `Bsdf::sample` and `Bsdf::pdf`.
+pub trait Scatter: Bsdf {
+    /// Sample a bounce, returning the flags of the lobe it came from, or
+    /// None when the ray is absorbed. Specular bounces must be told apart by
+    /// their flags: a material mixing delta and smooth lobes has a nonzero
+    /// `scattering_pdf` along its delta directions too.
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
-    ) -> bool;
+    ) -> Option<BsdfFlags> {
+        sample_scatter(self, r_in, rec, attenuation, scattered)
+    }
+
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::zero()
+    }
+
+    /// Density of the smooth lobes toward `scattered`; zero for specular
+    /// materials.
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.pdf(rec, -unit_vector(r_in.direction), unit_vector(scattered.direction))
+    }
+}
+
+fn sample_scatter<B: Bsdf + ?Sized>(
+    bsdf: &B,
+    r_in: &Ray,
+    rec: &HitRecord,
+    attenuation: &mut Color,
+    scattered: &mut Ray,
+) -> Option<BsdfFlags> {
+    let s = bsdf.sample(rec, -unit_vector(r_in.direction), sample_uniforms())?;
+    *attenuation = s.weight;
+    *scattered = r_in.spawn(rec.p, s.wi);
+    // From the first dispersive bounce on, the path follows a single
+    // wavelength.
+    if s.flags.contains(BsdfFlags::DISPERSIVE) && !r_in.dispersed {
+        *attenuation *= r_in.dispersion_weight();
+        scattered.dispersed = true;
+    }
+    Some(s.flags)
 }
 
 #[derive(Clone)]
@@ -22,6 +67,15 @@ pub enum Material {
     Lambertian(Lambertian),
     Metal(Metal),
     Dielectric(Dielectric),
+    DiffuseLight(DiffuseLight),
+    RoughConductor(RoughConductor),
+    RoughDielectric(RoughDielectric),
+    Principled(Box<Principled>),
+    Coated(Box<Coated>),
+    Subsurface(Subsurface),
+    NormalMapped(Box<NormalMapped>),
+    AlphaMasked(Box<AlphaMasked>),
+    Mix(Box<Mix>),
 }
 
 impl Material {
@@ -36,26 +90,252 @@ impl Material {
     pub fn new_image_tex(c: &str) -> Self { Material::Lambertian(Lambertian::new_img(c))}
 
     pub fn new_metal(albedo: Color, fuzz: f64) -> Self {
-        Material::Metal(Metal::new(albedo, fuzz))
+        Material::Metal(Metal::new(albedo, fuzz, None))
+    }
+
+    /// Metal under an interfering transparent `film`, like tempered steel.
+    pub fn new_thin_film_metal(albedo: Color, fuzz: f64, film: ThinFilm) -> Self {
+        Material::Metal(Metal::new(albedo, fuzz, Some(film)))
+    }
+
+    /// GGX conductor with complex index of refraction `eta + ik` per channel.
+    pub fn new_rough_conductor(eta: Color, k: Color, roughness: f64) -> Self {
+        Material::RoughConductor(RoughConductor::new(eta, k, roughness))
+    }
+
+    pub fn new_conductor(metal: Conductor, roughness: f64) -> Self {
+        let (eta, k) = metal.ior();
+        Material::new_rough_conductor(eta, k, roughness)
     }
 
     pub fn new_dielectric(ref_idx: f64) -> Self {
-        Material::Dielectric(Dielectric::new(ref_idx))
+        Material::Dielectric(Dielectric::new(Ior::Constant(ref_idx), Color::zero(), None))
+    }
+
+    /// Glass whose index of refraction varies with wavelength, splitting
+    /// white light.
+    pub fn new_dispersive_dielectric(ior: Ior) -> Self {
+        Material::Dielectric(Dielectric::new(ior, Color::zero(), None))
+    }
+
+    /// Glass whose interior absorbs light per unit length travelled,
+    /// following the Beer-Lambert law.
+    pub fn new_absorbing_dielectric(ref_idx: f64, absorption: Color) -> Self {
+        Material::Dielectric(Dielectric::new(Ior::Constant(ref_idx), absorption, None))
+    }
+
+    /// Colored glass that transmits `color` of the light entering it after
+    /// travelling `distance` inside.
+    pub fn new_colored_glass(ref_idx: f64, color: Color, distance: f64) -> Self {
+        let absorption = |c: f64| -c.ln() / distance;
+        let absorption = Color::from([absorption(color.x), absorption(color.y), absorption(color.z)]);
+        Material::new_absorbing_dielectric(ref_idx, absorption)
+    }
+
+    /// Glass under an interfering transparent `film`. A soap bubble is a
+    /// film on glass of index one.
+    pub fn new_thin_film_dielectric(ref_idx: f64, film: ThinFilm) -> Self {
+        Material::Dielectric(Dielectric::new(Ior::Constant(ref_idx), Color::zero(), Some(film)))
+    }
+
+    /// Frosted glass: GGX reflection and transmission with exact Fresnel.
+    pub fn new_rough_dielectric(ref_idx: f64, roughness: f64) -> Self {
+        Material::RoughDielectric(RoughDielectric::new(ref_idx, roughness))
+    }
+
+    pub fn new_principled(principled: Principled) -> Self {
+        Material::Principled(Box::new(principled))
+    }
+
+    pub fn new_coated(coated: Coated) -> Self {
+        Material::Coated(Box::new(coated))
+    }
+
+    /// Translucent solid like skin, wax or marble, whose inside scatters
+    /// light with surface `albedo` after `mean_free_path` on average, per
+    /// channel.
+    pub fn new_subsurface(albedo: Color, mean_free_path: Color, ref_idx: f64) -> Self {
+        Material::Subsurface(Subsurface::new(albedo, mean_free_path, ref_idx))
+    }
+
+    /// `base` with its shading normal taken from a tangent-space normal map.
+    pub fn new_normal_mapped(base: impl Into<Arc<Material>>, path: &str) -> Self {
+        Material::NormalMapped(Box::new(NormalMapped { base: base.into(), map: NormalMap::new_normal_map(path) }))
+    }
+
+    /// `base` on a surface displaced by a height map, `scale` high where the
+    /// map is white.
+    pub fn new_bump_mapped(base: impl Into<Arc<Material>>, path: &str, scale: f64) -> Self {
+        Material::NormalMapped(Box::new(NormalMapped { base: base.into(), map: NormalMap::new_bump_map(path, scale) }))
+    }
+
+    /// `base` cut out where `opacity` is below `threshold`, or, without a
+    /// threshold, let through at random with probability one minus opacity.
+    pub fn new_alpha_masked(base: impl Into<Arc<Material>>, opacity: Surface, threshold: Option<f64>) -> Self {
+        Material::AlphaMasked(Box::new(AlphaMasked { base: base.into(), opacity, threshold }))
+    }
+
+    /// `a` blended with `b`, which takes a share `weight`.
+    pub fn new_mix(a: impl Into<Arc<Material>>, b: impl Into<Arc<Material>>, weight: f64) -> Self {
+        Material::Mix(Box::new(Mix { a: a.into(), b: b.into(), weight: Surface::new_constant(weight) }))
+    }
+
+    /// `a` blended with `b`, which takes the share given by the red channel
+    /// of `weight`.
+    pub fn new_mix_tex(a: impl Into<Arc<Material>>, b: impl Into<Arc<Material>>, weight: Surface) -> Self {
+        Material::Mix(Box::new(Mix { a: a.into(), b: b.into(), weight }))
+    }
+
+    pub fn new_diffuse_light(emit: Color) -> Self {
+        Material::DiffuseLight(DiffuseLight::new(Surface::new_solid_color(emit)))
+    }
+
+    pub fn new_diffuse_light_tex(emit: Surface) -> Self {
+        Material::DiffuseLight(DiffuseLight::new(emit))
+    }
+}
+
+// Materials are described in RGB; spectral hits see their colors uplifted.
+// Metal and Dielectric may carry thin films, which are evaluated per
+// wavelength, and Coated, Subsurface, NormalMapped, AlphaMasked and Mix
+// defer to other materials, so these uplift their own colors.
+impl Bsdf for Material {
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        rec.uplift(match self {
+            Material::Lambertian(m) => m.eval(rec, wo, wi),
+            Material::Metal(m) => return m.eval(rec, wo, wi),
+            Material::Dielectric(m) => return m.eval(rec, wo, wi),
+            Material::DiffuseLight(m) => m.eval(rec, wo, wi),
+            Material::RoughConductor(m) => m.eval(rec, wo, wi),
+            Material::RoughDielectric(m) => m.eval(rec, wo, wi),
+            Material::Principled(m) => m.eval(rec, wo, wi),
+            Material::Coated(m) => return m.eval(rec, wo, wi),
+            Material::Subsurface(m) => return m.eval(rec, wo, wi),
+            Material::NormalMapped(m) => return m.base.eval(rec, wo, wi),
+            Material::AlphaMasked(m) => return m.base.eval(rec, wo, wi),
+            Material::Mix(m) => return m.eval(rec, wo, wi),
+        })
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let sample = match self {
+            Material::Lambertian(m) => m.sample(rec, wo, u),
+            Material::Metal(m) => return m.sample(rec, wo, u),
+            Material::Dielectric(m) => return m.sample(rec, wo, u),
+            Material::DiffuseLight(m) => m.sample(rec, wo, u),
+            Material::RoughConductor(m) => m.sample(rec, wo, u),
+            Material::RoughDielectric(m) => m.sample(rec, wo, u),
+            Material::Principled(m) => m.sample(rec, wo, u),
+            Material::Coated(m) => return m.sample(rec, wo, u),
+            Material::Subsurface(m) => return m.sample(rec, wo, u),
+            Material::NormalMapped(m) => return m.base.sample(rec, wo, u),
+            Material::AlphaMasked(m) => return m.base.sample(rec, wo, u),
+            Material::Mix(m) => return m.sample(rec, wo, u),
+        };
+        sample.map(|s| BsdfSample { weight: rec.uplift(s.weight), ..s })
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        match self {
+            Material::Lambertian(m) => m.pdf(rec, wo, wi),
+            Material::Metal(m) => m.pdf(rec, wo, wi),
+            Material::Dielectric(m) => m.pdf(rec, wo, wi),
+            Material::DiffuseLight(m) => m.pdf(rec, wo, wi),
+            Material::RoughConductor(m) => m.pdf(rec, wo, wi),
+            Material::RoughDielectric(m) => m.pdf(rec, wo, wi),
+            Material::Principled(m) => m.pdf(rec, wo, wi),
+            Material::Coated(m) => m.pdf(rec, wo, wi),
+            Material::Subsurface(m) => m.pdf(rec, wo, wi),
+            Material::NormalMapped(m) => m.base.pdf(rec, wo, wi),
+            Material::AlphaMasked(m) => m.base.pdf(rec, wo, wi),
+            Material::Mix(m) => m.pdf(rec, wo, wi),
+        }
+    }
+}
+
+impl Material {
+    /// Whether every lobe is a delta, so that `eval` and `pdf` vanish.
+    pub(crate) fn is_delta(&self) -> bool {
+        match self {
+            Material::Metal(_) | Material::Dielectric(_) | Material::Subsurface(_) => true,
+            Material::Coated(m) => m.base.is_delta(),
+            Material::NormalMapped(m) => m.base.is_delta(),
+            Material::AlphaMasked(m) => m.base.is_delta(),
+            Material::Mix(m) => m.a.is_delta() && m.b.is_delta(),
+            _ => false,
+        }
+    }
+
+    /// What happens to `r` inside the medium behind the surface it hits at
+    /// `rec`, before it gets there. Only subsurface materials hit from
+    /// inside have a medium.
+    pub(crate) fn medium_event(&self, r: &Ray, rec: &HitRecord) -> MediumEvent {
+        match self {
+            Material::Subsurface(m) if !rec.front_face => m.medium_event(r, rec),
+            Material::NormalMapped(m) => m.base.medium_event(r, rec),
+            Material::AlphaMasked(m) => m.base.medium_event(r, rec),
+            _ => MediumEvent { scattered: None, weight: Color::ones(), walk: None },
+        }
+    }
+
+    /// Whether a hit at `(u, v)` stands, rather than falling through a hole
+    /// in an alpha mask.
+    pub fn opaque(&self, u: f64, v: f64, p: &Point3) -> bool {
+        match self {
+            Material::AlphaMasked(m) => {
+                let alpha = m.opacity.opacity(u, v, p);
+                let kept = match m.threshold {
+                    Some(threshold) => alpha >= threshold,
+                    None => alpha >= 1.0 || path_sample!() < alpha,
+                };
+                kept && m.base.opaque(u, v, p)
+            }
+            Material::NormalMapped(m) => m.base.opaque(u, v, p),
+            _ => true,
+        }
     }
 }
 
+/// Outcome of crossing a medium toward a surface hit.
+pub(crate) struct MediumEvent {
+    /// Point and direction of a scattering event inside the medium, if the
+    /// ray did not reach the surface.
+    pub scattered: Option<(Point3, Vec3)>,
+    pub weight: Color,
+    /// The walk to continue on, None once outside the medium.
+    pub walk: Option<MediumWalk>,
+}
+
 impl Scatter for Material {
+    // Rays inside a subsurface material may scatter before they reach the
+    // boundary; such events are specular.
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
-    ) -> bool {
+    ) -> Option<BsdfFlags> {
+        let event = self.medium_event(r_in, rec);
+        if let Some((p, direction)) = event.scattered {
+            *attenuation = event.weight;
+            *scattered = r_in.spawn(p, direction);
+            scattered.walk = event.walk;
+            return Some(BsdfFlags::SPECULAR)
+        }
+        let flags = sample_scatter(self, r_in, rec, attenuation, scattered)?;
+        *attenuation *= event.weight;
+        scattered.walk = event.walk;
+        Some(flags)
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
         match self {
-            Material::Lambertian(m) => m.scatter(r_in, rec, attenuation, scattered),
-            Material::Metal(m) => m.scatter(r_in, rec, attenuation, scattered),
-            Material::Dielectric(m) => m.scatter(r_in, rec, attenuation, scattered),
+            Material::DiffuseLight(m) => m.emitted(u, v, p),
+            Material::NormalMapped(m) => m.base.emitted(u, v, p),
+            Material::AlphaMasked(m) => m.base.emitted(u, v, p),
+            Material::Mix(m) => lerp(m.a.emitted(u, v, p), m.b.emitted(u, v, p), m.share(u, v, p)),
+            _ => Color::zero(),
         }
     }
 }
@@ -73,92 +353,979 @@ impl Lambertian {
     fn new_img(c:&str) -> Self { Self { albedo: Surface::new_image_texture(c)}}
 }
 
-impl Scatter for Lambertian {
-    fn scatter(
-        &self,
-        _r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let scatter_direction = rec.normal + random_unit_vector();
-        *scattered = Ray::new(rec.p, scatter_direction, 0.0);
-        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
-        true
+impl Bsdf for Lambertian {
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        if dot(&rec.normal, &wo) <= 0.0 || dot(&rec.normal, &wi) <= 0.0 {
+            return Color::zero()
+        }
+        self.albedo.value(rec.u, rec.v, &rec.p) / PI
+    }
+
+    fn sample(&self, rec: &HitRecord, _wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let local = cosine_direction(u[1], u[2]);
+        Some(BsdfSample {
+            wi: ONB::build_from_w(rec.normal).local(local),
+            weight: self.albedo.value(rec.u, rec.v, &rec.p),
+            pdf: local.z / PI,
+            flags: BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION,
+        })
+    }
+
+    fn pdf(&self, rec: &HitRecord, _wo: Vec3, wi: Vec3) -> f64 {
+        let cosine = dot(&rec.normal, &wi);
+        if cosine < 0.0 { 0.0 } else { cosine / PI }
     }
 }
 
+impl Scatter for Lambertian {}
+
 #[derive(Clone)]
 pub struct Metal {
     albedo: Surface,
     fuzz: f64,
+    film: Option<ThinFilm>,
 }
 
 impl Metal {
-    fn new(albedo: Color, fuzz: f64) -> Self {
+    fn new(albedo: Color, fuzz: f64, film: Option<ThinFilm>) -> Self {
         Self {
             albedo: Surface::new_test_texture(albedo),
             fuzz: if fuzz < 1.0 { fuzz } else { 1.0 },
+            film,
         }
     }
 }
 
-impl Scatter for Metal {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let reflected = reflect(unit_vector(r_in.direction), rec.normal);
-        *scattered = Ray::new(rec.p, reflected + self.fuzz * random_in_unit_sphere(), 0.0);
-        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
-        dot(&scattered.direction, &rec.normal) > 0.0
+// Fuzz jitters the mirror direction by a point in a ball, which has no
+// closed-form density, so to integrators Metal stays a delta lobe.
+impl Bsdf for Metal {
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let wi = unit_vector(reflect(-wo, rec.normal) + self.fuzz * ball_point(u));
+        if dot(&wi, &rec.normal) <= 0.0 {
+            return None
+        }
+        let albedo = rec.uplift(self.albedo.value(rec.u, rec.v, &rec.p));
+        let weight = match &self.film {
+            Some(film) => film.reflectance(rec, dot(&wo, &rec.normal), 1.0, Substrate::Conductor(albedo)),
+            None => albedo,
+        };
+        Some(BsdfSample {
+            wi,
+            weight,
+            pdf: 1.0,
+            flags: BsdfFlags::SPECULAR | BsdfFlags::REFLECTION,
+        })
+    }
+}
+
+impl Scatter for Metal {}
+
+/// Measured metals for `Material::new_conductor`.
+#[derive(Clone, Copy, Debug)]
+pub enum Conductor {
+    Gold,
+    Copper,
+    Silver,
+    Aluminium,
+    Brass,
+}
+
+impl Conductor {
+    /// Complex index of refraction `(eta, k)` at the red, green and blue
+    /// primaries.
+    pub fn ior(self) -> (Color, Color) {
+        let (eta, k) = match self {
+            Conductor::Gold => ([0.143, 0.375, 1.442], [3.983, 2.386, 1.603]),
+            Conductor::Copper => ([0.200, 0.924, 1.102], [3.913, 2.453, 2.142]),
+            Conductor::Silver => ([0.155, 0.117, 0.138], [4.828, 3.122, 2.147]),
+            Conductor::Aluminium => ([1.657, 0.880, 0.521], [9.224, 6.270, 4.837]),
+            Conductor::Brass => ([0.444, 0.527, 1.094], [3.695, 2.765, 1.829]),
+        };
+        (Color::from(eta), Color::from(k))
+    }
+}
+
+/// Rough metal: GGX microfacets with conductor Fresnel, sampled from the
+/// visible normals.
+#[derive(Clone)]
+pub struct RoughConductor {
+    eta: Color,
+    k: Color,
+    distribution: Ggx,
+}
+
+impl RoughConductor {
+    fn new(eta: Color, k: Color, roughness: f64) -> Self {
+        Self { eta, k, distribution: Ggx::new(roughness) }
+    }
+}
+
+impl Bsdf for RoughConductor {
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let cos_o = dot(&rec.normal, &wo);
+        let cos_i = dot(&rec.normal, &wi);
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::zero()
+        }
+        let wh = unit_vector(wo + wi);
+        let d = self.distribution.d(dot(&rec.normal, &wh));
+        let g = self.distribution.g(cos_o, cos_i);
+        fresnel_conductor(dot(&wo, &wh), self.eta, self.k) * (d * g / (4.0 * cos_o * cos_i))
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let cos_o = dot(&rec.normal, &wo);
+        if cos_o <= 0.0 {
+            return None
+        }
+        let wh = self.distribution.sample_visible(rec.normal, wo, u[1], u[2]);
+        let wi = reflect(-wo, wh);
+        let cos_i = dot(&rec.normal, &wi);
+        if cos_i <= 0.0 {
+            return None
+        }
+        let cos_oh = dot(&wo, &wh);
+        let g = self.distribution.g(cos_o, cos_i) / self.distribution.g1(cos_o);
+        Some(BsdfSample {
+            wi,
+            weight: fresnel_conductor(cos_oh, self.eta, self.k) * g,
+            pdf: self.distribution.pdf_visible(rec.normal, wo, wh) / (4.0 * cos_oh),
+            flags: BsdfFlags::GLOSSY | BsdfFlags::REFLECTION,
+        })
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        if dot(&rec.normal, &wo) <= 0.0 || dot(&rec.normal, &wi) <= 0.0 {
+            return 0.0
+        }
+        let wh = unit_vector(wo + wi);
+        self.distribution.pdf_visible(rec.normal, wo, wh) / (4.0 * dot(&wo, &wh))
+    }
+}
+
+impl Scatter for RoughConductor {}
+
+/// Index of refraction as a function of wavelength.
+#[derive(Clone, Copy, Debug)]
+pub enum Ior {
+    Constant(f64),
+    /// `n = a + b / λ²`, with λ in micrometres.
+    Cauchy { a: f64, b: f64 },
+    /// `n² = 1 + Σ b λ² / (λ² - c)`, with λ in micrometres.
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Ior {
+    /// Wavelength of the sodium D line, where glasses are usually quoted
+    /// and where rays without a wavelength are refracted.
+    pub const REFERENCE_WAVELENGTH: f64 = 589.3;
+
+    /// Schott N-BK7 crown glass.
+    pub const BK7: Ior = Ior::Sellmeier {
+        b: [1.03961212, 0.231792344, 1.01046945],
+        c: [0.00600069867, 0.0200179144, 103.560653],
+    };
+    /// Schott SF11 dense flint glass, strongly dispersive.
+    pub const SF11: Ior = Ior::Sellmeier {
+        b: [1.73759695, 0.313747346, 1.89878101],
+        c: [0.013188707, 0.0623068142, 155.23629],
+    };
+    pub const FUSED_SILICA: Ior = Ior::Sellmeier {
+        b: [0.6961663, 0.4079426, 0.8974794],
+        c: [0.00467914826, 0.0135120631, 97.9340025],
+    };
+    pub const DIAMOND: Ior = Ior::Sellmeier {
+        b: [4.3356, 0.3306, 0.0],
+        c: [0.011236, 0.030625, 0.0],
+    };
+
+    /// Index at `wavelength` nanometres.
+    pub fn at(self, wavelength: f64) -> f64 {
+        let um = wavelength / 1000.0;
+        let um2 = um * um;
+        match self {
+            Ior::Constant(n) => n,
+            Ior::Cauchy { a, b } => a + b / um2,
+            Ior::Sellmeier { b, c } => {
+                let n2 = 1.0 + (0..3).map(|i| b[i] * um2 / (um2 - c[i])).sum::<f64>();
+                n2.sqrt()
+            }
+        }
+    }
+
+    pub fn is_dispersive(self) -> bool {
+        !matches!(self, Ior::Constant(_))
     }
 }
 
 #[derive(Clone)]
 pub struct Dielectric {
-    ref_idx: f64,
+    ior: Ior,
+    absorption: Color,
+    film: Option<ThinFilm>,
 }
 
 impl Dielectric {
-    fn new(ref_idx: f64) -> Self {
-        Self { ref_idx }
+    fn new(ior: Ior, absorption: Color, film: Option<ThinFilm>) -> Self {
+        Self { ior, absorption, film }
+    }
+
+    // Fraction of light surviving the path to a hit from inside, over the
+    // distance travelled since the previous hit.
+    fn transmittance(&self, rec: &HitRecord) -> Color {
+        if rec.front_face {
+            return Color::ones()
+        }
+        let channel = |sigma: f64| (-sigma * rec.distance).exp();
+        Color::from([channel(self.absorption.x), channel(self.absorption.y), channel(self.absorption.z)])
     }
 }
 
-impl Scatter for Dielectric {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        *attenuation = Color::ones();
+impl Bsdf for Dielectric {
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let ref_idx = self.ior.at(rec.channels.wavelength().unwrap_or(Ior::REFERENCE_WAVELENGTH));
         let etai_over_etat = if rec.front_face {
-            1.0 / self.ref_idx
+            1.0 / ref_idx
         } else {
-            self.ref_idx
+            ref_idx
         };
-        let unit_direction = unit_vector(r_in.direction);
-        let cos_theta = f64::min(dot(&-unit_direction, &rec.normal), 1.0);
+        let cos_theta = f64::min(dot(&wo, &rec.normal), 1.0);
         let sin_theta = f64::sqrt(1.0 - cos_theta.powi(2));
-        *scattered = if etai_over_etat * sin_theta > 1.0
-            || random_double!() < schlick(cos_theta, etai_over_etat)
-        {
-            let reflected = reflect(unit_direction, rec.normal);
-            Ray::new(rec.p, reflected, 0.0)
+        let reflectance = match &self.film {
+            _ if etai_over_etat * sin_theta > 1.0 => Color::ones(),
+            Some(film) => {
+                let (eta_i, eta_t) = if rec.front_face { (1.0, ref_idx) } else { (ref_idx, 1.0) };
+                film.reflectance(rec, cos_theta, eta_i, Substrate::Dielectric(eta_t))
+            }
+            None => Color::ones() * schlick(cos_theta, etai_over_etat),
+        };
+        // Reflection is picked with probability equal to the mean
+        // reflectance, which for a colorless one leaves only the absorption
+        // on either branch.
+        let p_reflect = (reflectance.x + reflectance.y + reflectance.z) / 3.0;
+        let transmittance = rec.uplift(self.transmittance(rec));
+        let dispersive = match rec.channels.wavelength() {
+            Some(_) if self.ior.is_dispersive() => BsdfFlags::DISPERSIVE,
+            _ => BsdfFlags::default(),
+        };
+        Some(if u[0] < p_reflect {
+            BsdfSample {
+                wi: reflect(-wo, rec.normal),
+                weight: transmittance * reflectance / p_reflect,
+                pdf: p_reflect,
+                flags: BsdfFlags::SPECULAR | BsdfFlags::REFLECTION | dispersive,
+            }
         } else {
-            let refracted = refract(unit_direction, rec.normal, etai_over_etat);
-            Ray::new(rec.p, refracted, 0.0)
+            BsdfSample {
+                wi: refract(-wo, rec.normal, etai_over_etat),
+                weight: transmittance * (Color::ones() - reflectance) / (1.0 - p_reflect),
+                pdf: 1.0 - p_reflect,
+                flags: BsdfFlags::SPECULAR | BsdfFlags::TRANSMISSION | dispersive,
+            }
+        })
+    }
+}
+
+impl Scatter for Dielectric {}
+
+/// Rough glass after Walter et al. 2007: GGX microfacets that reflect or
+/// refract with exact Fresnel, sampled from the visible normals.
+///
+/// Like `Dielectric`, transmission does not rescale radiance by the squared
+/// ratio of the indices, so `eval` is not symmetric; the factors cancel
+/// across closed objects.
+#[derive(Clone)]
+pub struct RoughDielectric {
+    ref_idx: f64,
+    distribution: Ggx,
+}
+
+impl RoughDielectric {
+    fn new(ref_idx: f64, roughness: f64) -> Self {
+        Self { ref_idx, distribution: Ggx::new(roughness) }
+    }
+
+    // Normal on the side of `wo` and `eta_t / eta_i` seen from there.
+    fn side(&self, rec: &HitRecord, wo: Vec3) -> (Vec3, f64) {
+        let eta = if rec.front_face { self.ref_idx } else { 1.0 / self.ref_idx };
+        if dot(&rec.normal, &wo) >= 0.0 {
+            (rec.normal, eta)
+        } else {
+            (-rec.normal, 1.0 / eta)
+        }
+    }
+}
+
+impl Bsdf for RoughDielectric {
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let (n, eta) = self.side(rec, wo);
+        let cos_o = dot(&n, &wo);
+        let cos_i = dot(&n, &wi);
+        if cos_o <= 0.0 || cos_i == 0.0 {
+            return Color::zero()
+        }
+        let g = self.distribution.g(cos_o, cos_i);
+        let f = if cos_i > 0.0 {
+            let wh = unit_vector(wo + wi);
+            let fresnel = fresnel_dielectric(dot(&wo, &wh), eta);
+            fresnel * self.distribution.d(dot(&n, &wh)) * g / (4.0 * cos_o * cos_i)
+        } else {
+            match refraction_half_vector(n, eta, wo, wi) {
+                Some((wh, denom)) => {
+                    let (cos_oh, cos_ih) = (dot(&wo, &wh), dot(&wi, &wh));
+                    let transmittance = 1.0 - fresnel_dielectric(cos_oh, eta);
+                    transmittance * self.distribution.d(dot(&n, &wh)) * g * (cos_oh * cos_ih).abs()
+                        / (cos_o * cos_i.abs() * denom)
+                }
+                None => 0.0,
+            }
+        };
+        Color::ones() * f
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let (n, eta) = self.side(rec, wo);
+        let cos_o = dot(&n, &wo);
+        if cos_o <= 0.0 {
+            return None
+        }
+        let wh = self.distribution.sample_visible(n, wo, u[1], u[2]);
+        let cos_oh = dot(&wo, &wh);
+        if cos_oh <= 0.0 {
+            return None
+        }
+        let reflectance = fresnel_dielectric(cos_oh, eta);
+        let pdf_wh = self.distribution.pdf_visible(n, wo, wh);
+        // Choosing the lobe by Fresnel leaves the same weight, G / G1, on
+        // either branch.
+        let (wi, pdf, flags) = if u[0] < reflectance {
+            let wi = reflect(-wo, wh);
+            (wi, pdf_wh / (4.0 * cos_oh) * reflectance, BsdfFlags::GLOSSY | BsdfFlags::REFLECTION)
+        } else {
+            let wi = refract(-wo, wh, 1.0 / eta);
+            let cos_ih = dot(&wi, &wh);
+            let denom = (cos_ih + cos_oh / eta).powi(2);
+            (wi, pdf_wh * cos_ih.abs() / denom * (1.0 - reflectance), BsdfFlags::GLOSSY | BsdfFlags::TRANSMISSION)
+        };
+        let cos_i = dot(&n, &wi);
+        if (cos_i > 0.0) == flags.is_transmission() || cos_i == 0.0 {
+            return None
+        }
+        let g = self.distribution.g(cos_o, cos_i) / self.distribution.g1(cos_o);
+        Some(BsdfSample { wi, weight: Color::ones() * g, pdf, flags })
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        let (n, eta) = self.side(rec, wo);
+        let cos_i = dot(&n, &wi);
+        if dot(&n, &wo) <= 0.0 || cos_i == 0.0 {
+            return 0.0
+        }
+        if cos_i > 0.0 {
+            let wh = unit_vector(wo + wi);
+            let cos_oh = dot(&wo, &wh);
+            self.distribution.pdf_visible(n, wo, wh) / (4.0 * cos_oh) * fresnel_dielectric(cos_oh, eta)
+        } else {
+            match refraction_half_vector(n, eta, wo, wi) {
+                Some((wh, denom)) => {
+                    let (cos_oh, cos_ih) = (dot(&wo, &wh), dot(&wi, &wh));
+                    self.distribution.pdf_visible(n, wo, wh) * cos_ih.abs() / denom
+                        * (1.0 - fresnel_dielectric(cos_oh, eta))
+                }
+                None => 0.0,
+            }
+        }
+    }
+}
+
+impl Scatter for RoughDielectric {}
+
+/// Burley's principled BSDF (Disney 2012, with the 2015 transmission lobe):
+/// one material spanning plastics, metals and glass. Every parameter is a
+/// texture; scalar parameters read the red channel and lie in [0, 1].
+///
+/// `specular` sets the dielectric reflectance at normal incidence to
+/// `0.08 * specular`, which also fixes the index of refraction seen by
+/// transmission (1.5 at the default 0.5).
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Surface,
+    pub metallic: Surface,
+    pub roughness: Surface,
+    pub specular: Surface,
+    pub specular_tint: Surface,
+    pub sheen: Surface,
+    pub clearcoat: Surface,
+    pub clearcoat_gloss: Surface,
+    pub transmission: Surface,
+}
+
+// Lobes of a principled surface at one hit, seen from `wo`.
+struct PrincipledLobes {
+    n: Vec3,
+    eta: f64,
+    base: Color,
+    metallic: f64,
+    roughness: f64,
+    specular_tint: Color,
+    sheen: Color,
+    clearcoat: f64,
+    specular: Ggx,
+    coat: Gtr1,
+    // Weights of the diffuse and transmission lobes.
+    diffuse: f64,
+    transmission: f64,
+    // Chance of sampling the diffuse, specular, clearcoat and transmission
+    // lobes.
+    probs: [f64; 4],
+}
+
+impl Principled {
+    /// A rough dielectric of the given color; set the other fields to taste.
+    pub fn new(base_color: Surface) -> Self {
+        Self {
+            base_color,
+            metallic: Surface::new_constant(0.0),
+            roughness: Surface::new_constant(0.5),
+            specular: Surface::new_constant(0.5),
+            specular_tint: Surface::new_constant(0.0),
+            sheen: Surface::new_constant(0.0),
+            clearcoat: Surface::new_constant(0.0),
+            clearcoat_gloss: Surface::new_constant(1.0),
+            transmission: Surface::new_constant(0.0),
+        }
+    }
+
+    fn lobes(&self, rec: &HitRecord, wo: Vec3) -> PrincipledLobes {
+        let scalar = |s: &Surface| clamp(s.value(rec.u, rec.v, &rec.p).x, 0.0, 1.0);
+        let base = self.base_color.value(rec.u, rec.v, &rec.p);
+        let metallic = scalar(&self.metallic);
+        let transmission = scalar(&self.transmission);
+        let r0 = (0.08 * scalar(&self.specular)).sqrt();
+        let ior = ((1.0 + r0) / (1.0 - r0)).max(1.001);
+        let above = dot(&rec.normal, &wo) >= 0.0;
+        let n = if above { rec.normal } else { -rec.normal };
+        let outside = rec.front_face == above;
+        let eta = if outside { ior } else { 1.0 / ior };
+        // Hue of the base color at unit luminance.
+        let luminance = 0.2126 * base.x + 0.7152 * base.y + 0.0722 * base.z;
+        let hue = if luminance > 0.0 { base / luminance } else { Color::ones() };
+        let specular_tint = lerp(Color::ones(), hue, scalar(&self.specular_tint));
+        // Only the outer surface carries the diffuse base, sheen and coat.
+        let (diffuse, clearcoat) = if outside {
+            ((1.0 - metallic) * (1.0 - transmission), 0.25 * scalar(&self.clearcoat))
+        } else {
+            (0.0, 0.0)
+        };
+        let transmission = (1.0 - metallic) * transmission;
+        let total = diffuse + 1.0 + clearcoat + transmission;
+        PrincipledLobes {
+            n,
+            eta,
+            base,
+            metallic,
+            roughness: scalar(&self.roughness),
+            specular_tint,
+            sheen: lerp(Color::ones(), hue, 0.5) * scalar(&self.sheen),
+            clearcoat,
+            specular: Ggx::new(scalar(&self.roughness)),
+            coat: Gtr1::new(0.1 + (0.001 - 0.1) * scalar(&self.clearcoat_gloss)),
+            diffuse,
+            transmission,
+            probs: [diffuse / total, 1.0 / total, clearcoat / total, transmission / total],
+        }
+    }
+}
+
+impl PrincipledLobes {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        let cos_o = dot(&self.n, &wo);
+        let cos_i = dot(&self.n, &wi);
+        if cos_o <= 0.0 || cos_i == 0.0 {
+            return Color::zero()
+        }
+        if cos_i < 0.0 {
+            return match refraction_half_vector(self.n, self.eta, wo, wi) {
+                Some((wh, denom)) if self.transmission > 0.0 => {
+                    let (cos_oh, cos_ih) = (dot(&wo, &wh), dot(&wi, &wh));
+                    let transmittance = 1.0 - fresnel_dielectric(cos_oh, self.eta);
+                    let d = self.specular.d(dot(&self.n, &wh));
+                    let g = self.specular.g(cos_o, cos_i);
+                    self.base
+                        * (self.transmission * transmittance * d * g * (cos_oh * cos_ih).abs()
+                            / (cos_o * cos_i.abs() * denom))
+                }
+                _ => Color::zero(),
+            }
+        }
+        let wh = unit_vector(wo + wi);
+        let (cos_h, cos_oh) = (dot(&self.n, &wh), dot(&wo, &wh));
+        let dielectric = self.specular_tint * fresnel_dielectric(cos_oh, self.eta);
+        let fresnel = lerp(dielectric, fresnel_schlick(cos_oh, self.base), self.metallic);
+        let mut f = fresnel
+            * (self.specular.d(cos_h) * self.specular.g(cos_o, cos_i) / (4.0 * cos_o * cos_i));
+        if self.diffuse > 0.0 {
+            // Burley's diffuse brightens grazing angles on rough surfaces
+            // and darkens them on smooth ones.
+            let f90 = 0.5 + 2.0 * self.roughness * cos_oh * cos_oh;
+            let retro = |cos: f64| 1.0 + (f90 - 1.0) * (1.0 - cos).powi(5);
+            let diffuse = self.base * (retro(cos_o) * retro(cos_i) / PI);
+            f += (diffuse + self.sheen * (1.0 - cos_oh).powi(5)) * self.diffuse;
+        }
+        if self.clearcoat > 0.0 {
+            let fresnel = fresnel_schlick(cos_oh, Color::ones() * 0.04).x;
+            let g = Ggx::new(0.5).g(cos_o, cos_i);
+            f += Color::ones() * (self.clearcoat * fresnel * self.coat.d(cos_h) * g / (4.0 * cos_o * cos_i));
+        }
+        f
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let cos_i = dot(&self.n, &wi);
+        if dot(&self.n, &wo) <= 0.0 || cos_i == 0.0 {
+            return 0.0
+        }
+        let [diffuse, specular, clearcoat, transmission] = self.probs;
+        if cos_i < 0.0 {
+            return match refraction_half_vector(self.n, self.eta, wo, wi) {
+                Some((wh, denom)) => {
+                    transmission * self.specular.pdf_visible(self.n, wo, wh) * dot(&wi, &wh).abs() / denom
+                }
+                None => 0.0,
+            }
+        }
+        let wh = unit_vector(wo + wi);
+        let cos_oh = dot(&wo, &wh);
+        diffuse * cos_i / PI
+            + (specular * self.specular.pdf_visible(self.n, wo, wh) + clearcoat * self.coat.pdf(self.n, wh))
+                / (4.0 * cos_oh)
+    }
+}
+
+impl Bsdf for Principled {
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        self.lobes(rec, wo).eval(wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let lobes = self.lobes(rec, wo);
+        let n = lobes.n;
+        if dot(&n, &wo) <= 0.0 {
+            return None
+        }
+        let [diffuse, specular, clearcoat, _] = lobes.probs;
+        let (wi, flags) = if u[0] < diffuse {
+            let wi = ONB::build_from_w(n).local(cosine_direction(u[1], u[2]));
+            (wi, BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION)
+        } else if u[0] < diffuse + specular {
+            let wh = lobes.specular.sample_visible(n, wo, u[1], u[2]);
+            (reflect(-wo, wh), BsdfFlags::GLOSSY | BsdfFlags::REFLECTION)
+        } else if u[0] < diffuse + specular + clearcoat {
+            let wh = lobes.coat.sample(n, u[1], u[2]);
+            (reflect(-wo, wh), BsdfFlags::GLOSSY | BsdfFlags::REFLECTION)
+        } else {
+            let wh = lobes.specular.sample_visible(n, wo, u[1], u[2]);
+            let cos_oh = dot(&wo, &wh);
+            if cos_oh <= 0.0 || (1.0 - cos_oh * cos_oh) >= lobes.eta * lobes.eta {
+                return None
+            }
+            (refract(-wo, wh, 1.0 / lobes.eta), BsdfFlags::GLOSSY | BsdfFlags::TRANSMISSION)
+        };
+        let cos_i = dot(&n, &wi);
+        if (cos_i > 0.0) == flags.is_transmission() || cos_i == 0.0 {
+            return None
+        }
+        // The other lobes may also reach `wi`, so weigh by the full mixture.
+        let pdf = lobes.pdf(wo, wi);
+        if pdf <= 0.0 {
+            return None
+        }
+        Some(BsdfSample { wi, weight: lobes.eval(wo, wi) * (cos_i.abs() / pdf), pdf, flags })
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.lobes(rec, wo).pdf(wo, wi)
+    }
+}
+
+impl Scatter for Principled {}
+
+// Bounces followed between the base and the underside of a coating.
+const COATING_BOUNCES: usize = 16;
+
+/// Dielectric coating over any `base` material, like the lacquer on
+/// plastic, the varnish on wood or the clearcoat of car paint.
+///
+/// The coating reflects off GGX microfacets and refracts along the surface
+/// normal. Below it, light bounces between the base and the underside of
+/// the coating, losing `absorption` per unit length on every crossing of
+/// the `thickness`. `eval` follows these bounces with a random walk, so it
+/// returns an unbiased estimate rather than an exact value. The base is
+/// treated as opaque; over a delta base such as `Metal` the whole material
+/// is a delta lobe.
+#[derive(Clone)]
+pub struct Coated {
+    pub base: Arc<Material>,
+    pub ior: f64,
+    pub roughness: f64,
+    pub thickness: f64,
+    pub absorption: Color,
+}
+
+impl Coated {
+    /// A smooth, clear coat of index 1.5; set the other fields to taste.
+    pub fn new(base: impl Into<Arc<Material>>) -> Self {
+        Self { base: base.into(), ior: 1.5, roughness: 0.0, thickness: 1.0, absorption: Color::zero() }
+    }
+
+    // Fraction of light surviving one crossing of the coating at `cos` to
+    // the normal.
+    fn crossing(&self, rec: &HitRecord, cos: f64) -> Color {
+        let tau = rec.uplift(self.absorption) * (self.thickness / cos);
+        Color::from([(-tau.x).exp(), (-tau.y).exp(), (-tau.z).exp()])
+    }
+
+    // Direction under the coating, facing away from the base, that refracts
+    // to `w` above it.
+    fn inside(&self, n: Vec3, w: Vec3) -> Vec3 {
+        -refract(-w, n, 1.0 / self.ior)
+    }
+
+    // Direction above the coating that `w` under it refracts to, if it is
+    // not reflected back whole.
+    fn outside(&self, n: Vec3, w: Vec3) -> Option<Vec3> {
+        let cos = dot(&n, &w);
+        if self.ior * self.ior * (1.0 - cos * cos) >= 1.0 {
+            return None
+        }
+        Some(refract(w, -n, self.ior))
+    }
+
+    // Probability of sampling the coating's own reflection, which is sharp
+    // and usually brighter than the base.
+    fn highlight_probability(&self, cos_o: f64) -> f64 {
+        0.25 + 0.75 * fresnel_dielectric(cos_o, self.ior)
+    }
+
+    // Light leaving the base toward `wo` per unit arriving from `wi`, both
+    // under the coating, summed over the bounces in between.
+    fn internal(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let n = rec.normal;
+        let mut estimate = Color::zero();
+        let mut beta = Color::ones();
+        let mut w = wo;
+        for depth in 0..COATING_BOUNCES {
+            estimate += beta * self.base.eval(rec, w, wi);
+            let s = match self.base.sample(rec, w, sample_uniforms()) {
+                Some(s) if dot(&n, &s.wi) > 0.0 => s,
+                _ => break,
+            };
+            let cos = dot(&n, &s.wi);
+            let crossing = self.crossing(rec, cos);
+            beta *= s.weight * crossing * crossing * fresnel_dielectric(cos, 1.0 / self.ior);
+            w = reflect(-s.wi, n);
+            if depth >= 2 {
+                let q = beta.x.max(beta.y).max(beta.z).min(0.95);
+                if path_sample!() >= q {
+                    break
+                }
+                beta = beta / q;
+            }
+        }
+        estimate
+    }
+
+    // Random walk through the coating over a delta base, leaving through the
+    // coating with the probability that it transmits.
+    fn sample_delta(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let n = rec.normal;
+        let cos_o = dot(&n, &wo);
+        let p = self.highlight_probability(cos_o);
+        if u[0] < p {
+            let ggx = Ggx::new(self.roughness);
+            let wh = ggx.sample_visible(n, wo, u[1], u[2]);
+            let wi = reflect(-wo, wh);
+            let cos_i = dot(&n, &wi);
+            if cos_i <= 0.0 {
+                return None
+            }
+            let weight = fresnel_dielectric(dot(&wo, &wh), self.ior) * ggx.g(cos_o, cos_i) / (ggx.g1(cos_o) * p);
+            return Some(BsdfSample {
+                wi,
+                weight: Color::ones() * weight,
+                pdf: p,
+                flags: BsdfFlags::SPECULAR | BsdfFlags::REFLECTION,
+            })
+        }
+        let mut w = self.inside(n, wo);
+        let mut beta = self.crossing(rec, dot(&n, &w)) * ((1.0 - fresnel_dielectric(cos_o, self.ior)) / (1.0 - p));
+        for _ in 0..COATING_BOUNCES {
+            let s = self.base.sample(rec, w, sample_uniforms())?;
+            let cos = dot(&n, &s.wi);
+            if cos <= 0.0 {
+                return None
+            }
+            beta *= s.weight * self.crossing(rec, cos);
+            if path_sample!() >= fresnel_dielectric(cos, 1.0 / self.ior) {
+                return Some(BsdfSample {
+                    wi: self.outside(n, s.wi)?,
+                    weight: beta,
+                    pdf: 1.0 - p,
+                    flags: BsdfFlags::SPECULAR | BsdfFlags::REFLECTION,
+                })
+            }
+            beta *= self.crossing(rec, cos);
+            w = reflect(-s.wi, n);
+        }
+        None
+    }
+}
+
+impl Bsdf for Coated {
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let n = rec.normal;
+        let cos_o = dot(&n, &wo);
+        let cos_i = dot(&n, &wi);
+        if self.base.is_delta() || cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::zero()
+        }
+        let ggx = Ggx::new(self.roughness);
+        let wh = unit_vector(wo + wi);
+        let highlight =
+            fresnel_dielectric(dot(&wo, &wh), self.ior) * ggx.d(dot(&n, &wh)) * ggx.g(cos_o, cos_i) / (4.0 * cos_o * cos_i);
+        // Radiance is compressed into a narrower cone under the coating,
+        // hence the squared index.
+        let (wo_in, wi_in) = (self.inside(n, wo), self.inside(n, wi));
+        let through = (1.0 - fresnel_dielectric(cos_o, self.ior)) * (1.0 - fresnel_dielectric(cos_i, self.ior))
+            / (self.ior * self.ior);
+        let crossings = self.crossing(rec, dot(&n, &wo_in)) * self.crossing(rec, dot(&n, &wi_in));
+        Color::ones() * highlight + self.internal(rec, wo_in, wi_in) * crossings * through
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let n = rec.normal;
+        let cos_o = dot(&n, &wo);
+        if cos_o <= 0.0 {
+            return None
+        }
+        if self.base.is_delta() {
+            return self.sample_delta(rec, wo, u)
+        }
+        // Either the coating's reflection or a single bounce off the base,
+        // whose densities `pdf` adds up; `eval` then accounts for the rest.
+        let p = self.highlight_probability(cos_o);
+        let (wi, flags) = if u[0] < p {
+            let wh = Ggx::new(self.roughness).sample_visible(n, wo, u[1], u[2]);
+            (reflect(-wo, wh), BsdfFlags::GLOSSY | BsdfFlags::REFLECTION)
+        } else {
+            let s = self.base.sample(rec, self.inside(n, wo), [(u[0] - p) / (1.0 - p), u[1], u[2]])?;
+            if dot(&n, &s.wi) <= 0.0 {
+                return None
+            }
+            let lobe = if s.flags.contains(BsdfFlags::DIFFUSE) { BsdfFlags::DIFFUSE } else { BsdfFlags::GLOSSY };
+            (self.outside(n, s.wi)?, lobe | BsdfFlags::REFLECTION)
+        };
+        let cos_i = dot(&n, &wi);
+        let pdf = self.pdf(rec, wo, wi);
+        if cos_i <= 0.0 || pdf <= 0.0 {
+            return None
+        }
+        Some(BsdfSample { wi, weight: self.eval(rec, wo, wi) * (cos_i / pdf), pdf, flags })
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        let n = rec.normal;
+        let cos_o = dot(&n, &wo);
+        let cos_i = dot(&n, &wi);
+        if self.base.is_delta() || cos_o <= 0.0 || cos_i <= 0.0 {
+            return 0.0
+        }
+        let wh = unit_vector(wo + wi);
+        let highlight = Ggx::new(self.roughness).pdf_visible(n, wo, wh) / (4.0 * dot(&wo, &wh));
+        // Solid angle widens by the squared index on the way out.
+        let wi_in = self.inside(n, wi);
+        let base = self.base.pdf(rec, self.inside(n, wo), wi_in) * cos_i / (self.ior * self.ior * dot(&n, &wi_in));
+        let p = self.highlight_probability(cos_o);
+        p * highlight + (1.0 - p) * base
+    }
+}
+
+impl Scatter for Coated {}
+
+/// Homogeneous medium with isotropic scattering inside a clear dielectric
+/// boundary, rendered by following rays on a random walk through it.
+///
+/// The boundary must be closed, with normals pointing out, and not overlap
+/// other objects. Light may scatter hundreds of times before leaving a
+/// bright material, so it needs a large `max_depth`.
+#[derive(Clone)]
+pub struct Subsurface {
+    /// Fraction of the light reaching a scattering event that scatters.
+    single_scattering_albedo: Color,
+    /// Extinction coefficient per channel, the inverse mean free path.
+    sigma_t: Color,
+    boundary: Dielectric,
+}
+
+impl Subsurface {
+    fn new(albedo: Color, mean_free_path: Color, ref_idx: f64) -> Self {
+        // Chiang et al. 2016 fit of the single-scattering albedo whose
+        // random walk in a semi-infinite slab reflects `albedo`.
+        let invert = |a: f64| {
+            let a = a.clamp(0.0, 1.0);
+            1.0 - (4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt()).powi(2)
+        };
+        let sigma_t = |d: f64| 1.0 / d.max(1e-6);
+        Self {
+            single_scattering_albedo: Color::from([invert(albedo.x), invert(albedo.y), invert(albedo.z)]),
+            sigma_t: Color::from([sigma_t(mean_free_path.x), sigma_t(mean_free_path.y), sigma_t(mean_free_path.z)]),
+            boundary: Dielectric::new(Ior::Constant(ref_idx), Color::zero(), None),
+        }
+    }
+
+    // Distances follow the extinction of one channel picked for the whole
+    // walk. Weighting by the mean density of the walk under every channel
+    // (the balance heuristic) bounds the weights by three, however long
+    // the walk gets.
+    fn medium_event(&self, r: &Ray, rec: &HitRecord) -> MediumEvent {
+        let sigma_t = rec.uplift(self.sigma_t);
+        let walk = r.walk.unwrap_or_else(|| MediumWalk {
+            channel: ((path_sample!() * 3.0) as usize).min(2),
+            pdf: Color::ones(),
+        });
+        let distance = rec.distance;
+        let d = -(1.0 - path_sample!()).ln() / sigma_t[walk.channel];
+        let transmittance = |d: f64| {
+            Color::from([(-sigma_t.x * d).exp(), (-sigma_t.y * d).exp(), (-sigma_t.z * d).exp()])
+        };
+        let (scattered, f, pdf) = if d < distance {
+            let t = transmittance(d);
+            let p = r.origin + unit_vector(r.direction) * d;
+            (Some((p, sphere_direction(path_sample!(), path_sample!()))), rec.uplift(self.single_scattering_albedo) * sigma_t * t, sigma_t * t)
+        } else {
+            let t = transmittance(distance);
+            (None, t, t)
+        };
+        // Densities are kept relative to their mean, which cancels out.
+        let pdf = walk.pdf * pdf;
+        let mean = (pdf.x + pdf.y + pdf.z) / 3.0;
+        MediumEvent {
+            scattered,
+            weight: f / mean,
+            walk: Some(MediumWalk { channel: walk.channel, pdf: pdf / mean }),
+        }
+    }
+}
+
+// The surface itself is the clear boundary; the medium is handled by
+// `Material::medium_event`.
+impl Bsdf for Subsurface {
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        self.boundary.eval(rec, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        self.boundary.sample(rec, wo, u)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.boundary.pdf(rec, wo, wi)
+    }
+}
+
+impl Scatter for Subsurface {}
+
+/// `base` under a normal or bump map. Hits unwrap it, leaving the bent
+/// normal and `base` on the `HitRecord`.
+#[derive(Clone)]
+pub struct NormalMapped {
+    pub base: Arc<Material>,
+    pub map: NormalMap,
+}
+
+/// `base` with holes where `opacity` is low. Shapes ask `Material::opaque`
+/// before taking a hit, and hits unwrap it like `NormalMapped`.
+#[derive(Clone)]
+pub struct AlphaMasked {
+    pub base: Arc<Material>,
+    pub opacity: Surface,
+    pub threshold: Option<f64>,
+}
+
+/// Blend of two materials, `b` taking the share `weight` and `a` the rest.
+/// Normal maps and alpha masks only apply from outside a mix, and media
+/// inside either material are ignored.
+#[derive(Clone)]
+pub struct Mix {
+    pub a: Arc<Material>,
+    pub b: Arc<Material>,
+    pub weight: Surface,
+}
+
+impl Mix {
+    fn share(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        clamp(self.weight.value(u, v, p).x, 0.0, 1.0)
+    }
+}
+
+impl Bsdf for Mix {
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let w = self.share(rec.u, rec.v, &rec.p);
+        lerp(self.a.eval(rec, wo, wi), self.b.eval(rec, wo, wi), w)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let w = self.share(rec.u, rec.v, &rec.p);
+        // The first number picks a material, and is stretched back over
+        // [0, 1) for it.
+        let (picked, other, p, u0) = if u[0] < w {
+            (&self.b, &self.a, w, u[0] / w)
+        } else {
+            (&self.a, &self.b, 1.0 - w, (u[0] - w) / (1.0 - w))
         };
-        true
+        let s = picked.sample(rec, wo, [u0, u[1], u[2]])?;
+        if s.flags.is_specular() {
+            return Some(BsdfSample { pdf: p * s.pdf, ..s })
+        }
+        // The other material may also reach `wi`, so weigh by the mixture.
+        let cos_i = dot(&rec.normal, &s.wi).abs();
+        let pdf = p * s.pdf + (1.0 - p) * other.pdf(rec, wo, s.wi);
+        let f_cos = s.weight * (p * s.pdf) + other.eval(rec, wo, s.wi) * ((1.0 - p) * cos_i);
+        Some(BsdfSample { wi: s.wi, weight: f_cos / pdf, pdf, flags: s.flags })
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        let w = self.share(rec.u, rec.v, &rec.p);
+        (1.0 - w) * self.a.pdf(rec, wo, wi) + w * self.b.pdf(rec, wo, wi)
     }
 }
 
+impl Scatter for Mix {}
+
+#[derive(Clone)]
+pub struct DiffuseLight {
+    emit: Surface,
+}
+
+impl DiffuseLight {
+    fn new(emit: Surface) -> Self {
+        Self { emit }
+    }
+}
+
+// Lights absorb whatever reaches them.
+impl Bsdf for DiffuseLight {}
+
+impl Scatter for DiffuseLight {
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.emit.value(u, v, p)
+    }
+}
+
+fn lerp(a: Color, b: Color, t: f64) -> Color {
+    a * (1.0 - t) + b * t
+}
+
 fn schlick(cosine: f64, ref_idx: f64) -> f64 {
     let r0 = ((1.0 - ref_idx) / (1.0 + ref_idx)).powi(2);
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
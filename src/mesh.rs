@@ -6,7 +6,7 @@ use crate::{
     aabb::Aabb,
     utils::{fmax, fmin}
 };
-use std::f64::consts::PI;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Mesh {
@@ -16,11 +16,11 @@ pub struct Mesh {
     n0: Point3,
     n1: Point3,
     n2: Point3,
-    material: Material,
+    material: Arc<Material>,
 }
 
 impl Mesh {
-    pub fn new(a0: Point3, a1: Point3, a2: Point3, n0: Point3, n1: Point3, n2: Point3, material: Material) -> Self {
+    pub fn new(a0: Point3, a1: Point3, a2: Point3, n0: Point3, n1: Point3, n2: Point3, material: impl Into<Arc<Material>>) -> Self {
         Self {
             a0,
             a1,
@@ -28,16 +28,9 @@ impl Mesh {
             n0,
             n1,
             n2,
-            material
+            material: material.into()
         }
     }
-
-    fn get_sphere_uv(p: &Point3, u: &mut f64, v: &mut f64) {
-        let phi = p.z.atan2(p.x);
-        let theta = p.y.asin();
-        *u = 1.0 - (phi + PI) / (2.0 * PI);
-        *v = (theta + PI / 2.0) / PI;
-    }
 }
 
 impl Hittable for Mesh {
@@ -53,15 +46,30 @@ impl Hittable for Mesh {
         if res.y<0.0 || res.z<0.0 || res.y+res.z>1.0 || res.x<t_min || res.x>t_max {
             return false
         }
+        if !self.material.opaque(res.y, res.z, &r.at(res.x)) {
+            return false
+        }
 
         rec.t = res.x;
         rec.p = r.at(rec.t);
         //let outward_normal = unit_vector(cross(e1, e2));
         let outward_normal = unit_vector((1.0-res.y-res.z)*self.n0 + res.y*self.n1 + res.z*self.n2);
-        rec.set_face_normal(r, &outward_normal);
-        Mesh::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
-        rec.material = self.material.clone();
-        return true;
+        // Which side was hit is decided by the flat face, turned toward the
+        // vertex normals, as the smooth normal may face a ray entering
+        // near the silhouette.
+        let mut face_normal = cross(e1, e2);
+        if dot(&face_normal, &outward_normal) < 0.0 {
+            face_normal = -face_normal;
+        }
+        rec.set_face_normal(r, &face_normal);
+        rec.normal = if rec.front_face { outward_normal } else { -outward_normal };
+        // Barycentric coordinates, so that textures span every triangle.
+        rec.u = res.y;
+        rec.v = res.z;
+        rec.dpdu = e1;
+        rec.dpdv = e2;
+        rec.set_material(&self.material);
+        true
     }
 
     fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
@@ -69,7 +77,7 @@ impl Hittable for Mesh {
         let y_mx = fmax(fmax(self.a0.y, self.a1.y),self.a2.y);
         let z_mx = fmax(fmax(self.a0.z, self.a1.z),self.a2.z);
         let x_mn = fmin(fmin(self.a0.x, self.a1.x),self.a2.x);
-        let y_mn = fmax(fmin(self.a0.y, self.a1.y),self.a2.y);
+        let y_mn = fmin(fmin(self.a0.y, self.a1.y),self.a2.y);
         let z_mn = fmin(fmin(self.a0.z, self.a1.z),self.a2.z);
         output_box.modify(Point3::from([x_mn, y_mn, z_mn]), Point3::from([x_mx, y_mx, z_mx]));
         true
@@ -0,0 +1,178 @@
+use crate::{
+    onb::ONB,
+    utils::PI,
+    vec3::{cross, dot, unit_vector, Color, Vec3},
+};
+
+// Below this width the distribution is too sharp to evaluate reliably.
+const MIN_ALPHA: f64 = 1e-3;
+
+/// Isotropic GGX (Trowbridge-Reitz) microfacet distribution with the Smith
+/// height-correlated shadowing-masking term.
+///
+/// Cosines are taken against the macro surface normal `n`.
+#[derive(Clone, Copy, Debug)]
+pub struct Ggx {
+    pub alpha: f64,
+}
+
+impl Ggx {
+    /// Width `alpha = roughness²`, so roughness reads perceptually linear.
+    pub fn new(roughness: f64) -> Self {
+        Self { alpha: (roughness * roughness).max(MIN_ALPHA) }
+    }
+
+    /// Density of microfacet normals at angle `acos(cos_h)` from `n`.
+    pub fn d(&self, cos_h: f64) -> f64 {
+        if cos_h <= 0.0 {
+            return 0.0
+        }
+        let a2 = self.alpha * self.alpha;
+        let cos2 = cos_h * cos_h;
+        let tan2 = (1.0 - cos2) / cos2;
+        a2 / (PI * cos2 * cos2 * (a2 + tan2).powi(2))
+    }
+
+    pub fn lambda(&self, cos: f64) -> f64 {
+        let cos2 = cos * cos;
+        if cos2 >= 1.0 {
+            return 0.0
+        }
+        let tan2 = (1.0 - cos2) / cos2;
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Fraction of microfacets visible from a direction at `acos(cos)`.
+    pub fn g1(&self, cos: f64) -> f64 {
+        1.0 / (1.0 + self.lambda(cos))
+    }
+
+    /// Fraction visible from both `wo` and `wi`.
+    pub fn g(&self, cos_o: f64, cos_i: f64) -> f64 {
+        1.0 / (1.0 + self.lambda(cos_o) + self.lambda(cos_i))
+    }
+
+    /// Microfacet normal drawn from the normals visible from `wo` (Heitz 2018).
+    pub fn sample_visible(&self, n: Vec3, wo: Vec3, u1: f64, u2: f64) -> Vec3 {
+        let uvw = ONB::build_from_w(n);
+        let a = self.alpha;
+        // Stretch to the unit-roughness configuration, where visible normals
+        // are a projected disk, then unstretch the sampled normal.
+        let vh = unit_vector(Vec3::from([a * dot(&wo, &uvw.u), a * dot(&wo, &uvw.v), dot(&wo, &uvw.w)]));
+        let lensq = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if lensq > 0.0 {
+            Vec3::from([-vh.y, vh.x, 0.0]) / lensq.sqrt()
+        } else {
+            Vec3::from([1.0, 0.0, 0.0])
+        };
+        let t2 = cross(vh, t1);
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+        let local = unit_vector(Vec3::from([a * nh.x, a * nh.y, nh.z.max(1e-6)]));
+        uvw.local(local)
+    }
+
+    /// Density of `sample_visible` returning `wh`.
+    pub fn pdf_visible(&self, n: Vec3, wo: Vec3, wh: Vec3) -> f64 {
+        let cos_o = dot(&n, &wo);
+        if cos_o <= 0.0 {
+            return 0.0
+        }
+        self.g1(cos_o) * dot(&wo, &wh).max(0.0) * self.d(dot(&n, &wh)) / cos_o
+    }
+}
+
+/// Berry (GTR1) distribution used by the clearcoat lobe of Burley's
+/// principled BSDF; its long tail gives a sharp highlight a soft halo.
+#[derive(Clone, Copy, Debug)]
+pub struct Gtr1 {
+    pub alpha: f64,
+}
+
+impl Gtr1 {
+    pub fn new(alpha: f64) -> Self {
+        Self { alpha: alpha.max(MIN_ALPHA) }
+    }
+
+    pub fn d(&self, cos_h: f64) -> f64 {
+        if cos_h <= 0.0 {
+            return 0.0
+        }
+        let a2 = self.alpha * self.alpha;
+        (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h * cos_h))
+    }
+
+    /// Microfacet normal drawn proportionally to `d(cos_h) * cos_h`.
+    pub fn sample(&self, n: Vec3, u1: f64, u2: f64) -> Vec3 {
+        let a2 = self.alpha * self.alpha;
+        let cos2 = (1.0 - a2.powf(1.0 - u1)) / (1.0 - a2);
+        let sin = (1.0 - cos2).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+        ONB::build_from_w(n).local(Vec3::from([sin * phi.cos(), sin * phi.sin(), cos2.sqrt()]))
+    }
+
+    /// Density of `sample` returning `wh`.
+    pub fn pdf(&self, n: Vec3, wh: Vec3) -> f64 {
+        let cos_h = dot(&n, &wh);
+        self.d(cos_h) * cos_h
+    }
+}
+
+/// Half vector of a refracted pair, facing `n`, with the squared denominator
+/// of the transmission Jacobian; None if no microfacet refracts `wo` into
+/// `wi`. `eta = eta_t / eta_i` seen from `wo`.
+pub fn refraction_half_vector(n: Vec3, eta: f64, wo: Vec3, wi: Vec3) -> Option<(Vec3, f64)> {
+    let mut wh = unit_vector(wo + eta * wi);
+    if dot(&wh, &n) < 0.0 {
+        wh = -wh;
+    }
+    let (cos_oh, cos_ih) = (dot(&wo, &wh), dot(&wi, &wh));
+    if cos_oh <= 0.0 || cos_ih >= 0.0 {
+        return None
+    }
+    Some((wh, (cos_ih + cos_oh / eta).powi(2)))
+}
+
+/// Unpolarised Fresnel reflectance of a conductor with complex index of
+/// refraction `eta + ik`, per channel.
+pub fn fresnel_conductor(cos_i: f64, eta: Color, k: Color) -> Color {
+    let channel = |eta: f64, k: f64| {
+        let cos2 = cos_i * cos_i;
+        let sin2 = 1.0 - cos2;
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_i * a;
+        let rs = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        0.5 * (rp + rs)
+    };
+    Color::from([channel(eta.x, k.x), channel(eta.y, k.y), channel(eta.z, k.z)])
+}
+
+/// Unpolarised Fresnel reflectance of a smooth dielectric interface, for
+/// light arriving at `acos(cos_i)` from the side where `eta = eta_t / eta_i`.
+/// One under total internal reflection.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+/// Schlick's approximation with normal-incidence reflectance `f0`.
+pub fn fresnel_schlick(cos_i: f64, f0: Color) -> Color {
+    let w = (1.0 - cos_i).max(0.0).powi(5);
+    f0 * (1.0 - w) + Color::ones() * w
+}
@@ -0,0 +1,274 @@
+use crate::{
+    camera::Camera,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    path_tracer::path_color,
+    render::{camera_ray, film_pixel, film_to_rgb, Framebuffer, RenderSettings},
+    vec3::Color,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use std::cell::{Cell, RefCell};
+
+const BOOTSTRAP_SAMPLES: usize = 100_000;
+const CHAINS: usize = 1000;
+const SIGMA: f64 = 0.01;
+
+thread_local! {
+    static CHAIN_ACTIVE: Cell<bool> = const { Cell::new(false) };
+    static ACTIVE: RefCell<Option<PrimarySampler>> = const { RefCell::new(None) };
+}
+
+/// Uniform number for a random decision along a light path.
+///
+/// While a Metropolis chain traces a path on this thread it is the chain's
+/// next primary sample, so that the decision can be mutated; otherwise it
+/// comes from the thread RNG like `random_double!`.
+#[macro_export]
+macro_rules! path_sample {
+    () => {
+        if $crate::mlt::chain_active() {
+            $crate::mlt::next_sample()
+        } else {
+            rand::random::<f64>()
+        }
+    };
+}
+
+/// Whether a Metropolis chain is tracing a path on this thread, so that
+/// `path_sample!` should read `next_sample`.
+#[inline]
+pub fn chain_active() -> bool {
+    CHAIN_ACTIVE.with(|active| active.get())
+}
+
+/// Next uniform number of the active chain.
+///
+/// Numbers come from the chain's primary sample vector so that every random
+/// decision along the path can be mutated, or from the thread RNG when no
+/// chain is active.
+pub fn next_sample() -> f64 {
+    ACTIVE.with(|active| match active.borrow_mut().as_mut() {
+        Some(sampler) => sampler.sample(),
+        None => rand::random::<f64>(),
+    })
+}
+
+#[derive(Clone, Copy)]
+struct PrimarySample {
+    value: f64,
+    last_modified: u64,
+    value_backup: f64,
+    modify_backup: u64,
+}
+
+/// Kelemen-style primary sample vector, mutated lazily as it is read.
+///
+/// A large step replaces every coordinate with a fresh uniform number; a
+/// small step perturbs it with a Gaussian of width `SIGMA`. Coordinates not
+/// read for several iterations catch up on all their missed small steps at
+/// once the next time they are used.
+pub struct PrimarySampler {
+    rng: StdRng,
+    large_step_probability: f64,
+    samples: Vec<PrimarySample>,
+    iteration: u64,
+    large_step: bool,
+    last_large_step_iteration: u64,
+    index: usize,
+}
+
+impl PrimarySampler {
+    pub fn new(seed: u64, large_step_probability: f64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            large_step_probability,
+            samples: Vec::new(),
+            iteration: 0,
+            large_step: true,
+            last_large_step_iteration: 0,
+            index: 0,
+        }
+    }
+
+    pub fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = self.rng.gen::<f64>() < self.large_step_probability;
+        self.index = 0;
+    }
+
+    pub fn sample(&mut self) -> f64 {
+        let i = self.index;
+        self.index += 1;
+        if i == self.samples.len() {
+            // A coordinate this path never used before, e.g. after a longer
+            // bounce chain or a retried rejection loop, starts out uniform.
+            let value = self.rng.gen();
+            self.samples.push(PrimarySample {
+                value,
+                last_modified: self.iteration,
+                value_backup: value,
+                modify_backup: self.iteration,
+            });
+            return value
+        }
+        let x = &mut self.samples[i];
+        if x.last_modified < self.last_large_step_iteration {
+            x.value = self.rng.gen();
+            x.last_modified = self.last_large_step_iteration;
+        }
+        x.value_backup = x.value;
+        x.modify_backup = x.last_modified;
+        if self.large_step {
+            x.value = self.rng.gen();
+        } else {
+            let n_small = (self.iteration - x.last_modified) as f64;
+            // Box-Muller; the sum of n Gaussian steps is one of width sqrt(n).
+            let (u1, u2): (f64, f64) = (self.rng.gen(), self.rng.gen());
+            let normal = (-2.0 * (1.0 - u1).ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+            x.value += normal * SIGMA * n_small.sqrt();
+            x.value -= x.value.floor();
+        }
+        x.last_modified = self.iteration;
+        x.value
+    }
+
+    pub fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step_iteration = self.iteration;
+        }
+    }
+
+    pub fn reject(&mut self) {
+        for x in self.samples.iter_mut() {
+            if x.last_modified == self.iteration {
+                x.value = x.value_backup;
+                x.last_modified = x.modify_backup;
+            }
+        }
+        self.iteration -= 1;
+    }
+}
+
+/// Scalar importance of a path, its luminance.
+fn contribution(c: Color) -> f64 {
+    let y = 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+    if y.is_finite() { y.max(0.0) } else { 0.0 }
+}
+
+struct PathSample {
+    pixel: usize,
+    radiance: Color,
+}
+
+/// Trace one path with every random number taken from `sampler`. The first
+/// two numbers pick the film position.
+fn evaluate<H: Hittable>(
+    sampler: PrimarySampler,
+    settings: &RenderSettings,
+    lights: &HittableList,
+    cam: &Camera,
+    world: &H,
+) -> (PathSample, PrimarySampler) {
+    ACTIVE.with(|active| *active.borrow_mut() = Some(sampler));
+    CHAIN_ACTIVE.with(|active| active.set(true));
+    let (u, v) = (next_sample(), next_sample());
+    let r = camera_ray(settings, cam, u, v);
+    let l = path_color(&r, world, lights, &settings.background, settings.max_depth);
+    // Film RGB, so that spectral paths are weighed by luminance as well.
+    let radiance = film_to_rgb(settings, r.film(l));
+    CHAIN_ACTIVE.with(|active| active.set(false));
+    let sampler = ACTIVE.with(|active| active.borrow_mut().take()).unwrap();
+
+    (PathSample { pixel: film_pixel(settings, u, v), radiance }, sampler)
+}
+
+/// Primary sample space Metropolis light transport (Kelemen et al. 2002).
+///
+/// A bootstrap pass of independent paths estimates the image brightness and
+/// seeds `CHAINS` Markov chains in proportion to path contribution. Each
+/// chain then runs its share of `mutations_per_pixel * pixels` mutations,
+/// splatting both the proposed and current path weighted by the acceptance
+/// probability.
+pub fn render<H: Hittable + Sync>(
+    settings: &RenderSettings,
+    lights: &HittableList,
+    cam: &Camera,
+    world: &H,
+    mutations_per_pixel: usize,
+    large_step_probability: f64,
+) -> Framebuffer {
+    let mut fb = Framebuffer::new(settings.image_width, settings.image_height);
+    let pixels = fb.data.len();
+
+    let weights: Vec<f64> = (0..BOOTSTRAP_SAMPLES)
+        .into_par_iter()
+        .map(|i| {
+            let sampler = PrimarySampler::new(i as u64, large_step_probability);
+            contribution(evaluate(sampler, settings, lights, cam, world).0.radiance)
+        })
+        .collect();
+    let cdf: Vec<f64> = weights
+        .iter()
+        .scan(0.0, |sum, w| {
+            *sum += w;
+            Some(*sum)
+        })
+        .collect();
+    let total = cdf[cdf.len() - 1];
+    if total <= 0.0 {
+        return fb
+    }
+    let b = total / BOOTSTRAP_SAMPLES as f64;
+
+    let total_mutations = mutations_per_pixel * pixels;
+    let splats = (0..CHAINS)
+        .into_par_iter()
+        .fold(
+            || vec![Color::zero(); pixels],
+            |mut splats, chain| {
+                let mut rng = StdRng::seed_from_u64((BOOTSTRAP_SAMPLES + chain) as u64);
+                let target = rng.gen::<f64>() * total;
+                let seed = cdf.partition_point(|&c| c <= target).min(BOOTSTRAP_SAMPLES - 1);
+                let sampler = PrimarySampler::new(seed as u64, large_step_probability);
+                let (mut current, mut sampler) = evaluate(sampler, settings, lights, cam, world);
+                let mut current_c = contribution(current.radiance);
+
+                let mutations = total_mutations / CHAINS + (chain < total_mutations % CHAINS) as usize;
+                for _ in 0..mutations {
+                    sampler.start_iteration();
+                    let (proposed, s) = evaluate(sampler, settings, lights, cam, world);
+                    sampler = s;
+                    let proposed_c = contribution(proposed.radiance);
+                    let accept = if current_c > 0.0 { (proposed_c / current_c).min(1.0) } else { 1.0 };
+                    if accept > 0.0 && proposed_c > 0.0 {
+                        splats[proposed.pixel] += proposed.radiance * (accept / proposed_c);
+                    }
+                    if accept < 1.0 {
+                        splats[current.pixel] += current.radiance * ((1.0 - accept) / current_c);
+                    }
+                    if rng.gen::<f64>() < accept {
+                        current = proposed;
+                        current_c = proposed_c;
+                        sampler.accept();
+                    } else {
+                        sampler.reject();
+                    }
+                }
+                splats
+            },
+        )
+        .reduce(
+            || vec![Color::zero(); pixels],
+            |mut a, b| {
+                for (x, y) in a.iter_mut().zip(b) {
+                    *x += y;
+                }
+                a
+            },
+        );
+
+    let scale = b / mutations_per_pixel as f64;
+    fb.data = splats.into_iter().map(|c| c * scale).collect();
+    fb
+}
@@ -0,0 +1,189 @@
+use crate::{
+    hittable::{HitRecord, Hittable, Shape},
+    hittable_list::HittableList,
+    ray::Ray,
+    aabb::Aabb,
+    bvh::{bounds_of, primitive_boxes},
+    vec3::Point3,
+    utils::{fmax, fmin}
+};
+
+const MAX_DEPTH: usize = 16;
+const LEAF_SIZE: usize = 8;
+const NO_CHILD: u32 = u32::MAX;
+
+struct OctreeNode {
+    bbox: Aabb,
+    /// Primitives overlapping every octant stay at the node, the rest are
+    /// pushed down into each octant they touch.
+    start: u32,
+    count: u32,
+    children: [u32; 8],
+}
+
+/// Adaptive octree over the shapes of a `HittableList`.
+///
+/// Each node is the intersection of its octant with the bounds of the shapes
+/// it holds, and is split about its own center, so cells follow the geometry
+/// instead of a fixed grid. Straddling shapes are referenced from every
+/// octant they overlap.
+pub struct Octree {
+    primitives: Vec<Shape>,
+    indices: Vec<u32>,
+    nodes: Vec<OctreeNode>,
+}
+
+fn octant(min: Point3, center: Point3, max: Point3, i: usize, split: [bool; 3]) -> Aabb {
+    let pick = |bit: usize, a: usize| if !split[a] {
+        (min[a], max[a])
+    } else if i & bit == 0 {
+        (min[a], center[a])
+    } else {
+        (center[a], max[a])
+    };
+    let (x, y, z) = (pick(1, 0), pick(2, 1), pick(4, 2));
+    Aabb::new(Point3::from([x.0, y.0, z.0]), Point3::from([x.1, y.1, z.1]))
+}
+
+fn clip(a: &Aabb, b: &Aabb) -> Aabb {
+    let (a0, a1, b0, b1) = (a.min(), a.max(), b.min(), b.max());
+    Aabb::new(
+        Point3::from([fmax(a0.x, b0.x), fmax(a0.y, b0.y), fmax(a0.z, b0.z)]),
+        Point3::from([fmin(a1.x, b1.x), fmin(a1.y, b1.y), fmin(a1.z, b1.z)]),
+    )
+}
+
+impl Octree {
+    pub fn new(world: HittableList) -> Self {
+        let objects = world.objects;
+        let mut tree = Self { primitives: Vec::new(), indices: Vec::new(), nodes: Vec::new() };
+        if objects.is_empty() {
+            return tree
+        }
+        let boxes = primitive_boxes(&objects);
+        let prims: Vec<usize> = (0..objects.len()).collect();
+        let root = bounds_of(&boxes, &prims);
+        tree.build(&boxes, prims, root, 0);
+        tree.primitives = objects;
+        tree
+    }
+
+    fn build(&mut self, boxes: &[Aabb], prims: Vec<usize>, cell: Aabb, depth: usize) -> u32 {
+        let idx = self.nodes.len();
+        let bbox = clip(&cell, &bounds_of(boxes, &prims));
+        self.nodes.push(OctreeNode { bbox, start: 0, count: 0, children: [NO_CHILD; 8] });
+        if prims.len() <= LEAF_SIZE || depth == MAX_DEPTH {
+            self.store(idx, &prims);
+            return idx as u32
+        }
+
+        let (min, max) = (bbox.min(), bbox.max());
+        let center = bbox.centroid();
+        // An axis that most shapes straddle is left whole, since halving it
+        // would only copy them into both halves; in the flat logo scenes
+        // every sphere crosses the middle of the thin axis. The upper
+        // octants of an unsplit axis stay empty.
+        let split = [0, 1, 2].map(|a| {
+            let straddling = prims.iter().filter(|&&p| boxes[p].min()[a] < center[a] && boxes[p].max()[a] > center[a]).count();
+            2 * straddling <= prims.len()
+        });
+        if split == [false; 3] {
+            self.store(idx, &prims);
+            return idx as u32
+        }
+        let mut here = Vec::new();
+        let mut buckets: [Vec<usize>; 8] = Default::default();
+        for &p in prims.iter() {
+            // Shapes flat on a center plane fall to the lower side.
+            let (lo, hi) = (boxes[p].min(), boxes[p].max());
+            let sides = |a: usize| if split[a] {
+                ((lo[a] < center[a] || hi[a] <= center[a]) as usize, (hi[a] > center[a]) as usize)
+            } else {
+                (1, 0)
+            };
+            let (x, y, z) = (sides(0), sides(1), sides(2));
+            if [x, y, z].iter().zip(split.iter()).all(|(s, &split)| !split || *s == (1, 1)) {
+                here.push(p);
+                continue
+            }
+            for (i, bucket) in buckets.iter_mut().enumerate() {
+                let hit = |s: (usize, usize), bit: usize| if i & bit == 0 { s.0 } else { s.1 };
+                if hit(x, 1) & hit(y, 2) & hit(z, 4) == 1 {
+                    bucket.push(p);
+                }
+            }
+        }
+        drop(prims);
+
+        self.store(idx, &here);
+        for (i, bucket) in buckets.iter_mut().enumerate() {
+            if !bucket.is_empty() {
+                let cell = octant(min, center, max, i, split);
+                let child = self.build(boxes, std::mem::take(bucket), cell, depth + 1);
+                self.nodes[idx].children[i] = child;
+            }
+        }
+        idx as u32
+    }
+
+    fn store(&mut self, idx: usize, prims: &[usize]) {
+        self.nodes[idx].start = self.indices.len() as u32;
+        self.nodes[idx].count = prims.len() as u32;
+        self.indices.extend(prims.iter().map(|&p| p as u32));
+    }
+
+    fn hit_node(&self, idx: usize, near: usize, r: &Ray, t_min: f64, closest_so_far: &mut f64, rec: &mut HitRecord) -> bool {
+        let node = &self.nodes[idx];
+        if !node.bbox.hit(r, t_min, *closest_so_far) {
+            return false
+        }
+        let mut hit_anything = false;
+        let start = node.start as usize;
+        for &p in &self.indices[start..start + node.count as usize] {
+            if self.primitives[p as usize].hit(r, t_min, *closest_so_far, rec) {
+                hit_anything = true;
+                *closest_so_far = rec.t;
+            }
+        }
+        // XOR-ing the octant index with the octant the ray starts towards
+        // visits the children front to back.
+        for i in 0..8 {
+            let child = node.children[i ^ near];
+            if child != NO_CHILD && self.hit_node(child as usize, near, r, t_min, closest_so_far, rec) {
+                hit_anything = true;
+            }
+        }
+        hit_anything
+    }
+
+    pub fn len(&self) -> usize {
+        self.primitives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+}
+
+impl Hittable for Octree {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        if self.nodes.is_empty() {
+            return false
+        }
+        let near = (r.direction.x < 0.0) as usize
+            | ((r.direction.y < 0.0) as usize) << 1
+            | ((r.direction.z < 0.0) as usize) << 2;
+        let mut closest_so_far = t_max;
+        self.hit_node(0, near, r, t_min, &mut closest_so_far, rec)
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        match self.nodes.first() {
+            Some(node) => {
+                *output_box = node.bbox;
+                true
+            }
+            None => false,
+        }
+    }
+}
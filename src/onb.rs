@@ -1,4 +1,4 @@
-use crate::vec3::Vec3;
+use crate::vec3::{cross, unit_vector, Vec3};
 
 pub struct ONB {
     pub u: Vec3,
@@ -8,14 +8,14 @@ pub struct ONB {
 
 impl ONB {
     pub fn build_from_w(normal: Vec3) -> ONB {
-        let w = (normal).normalize();
+        let w = unit_vector(normal);
         let a: Vec3 = if (w.x).abs() > 0.9 {
             Vec3::new(0.0, 1.0, 0.0)
         } else {
             Vec3::new(1.0, 0.0, 0.0)
         };
-        let v = (w.cross(&a)).normalize();
-        let u = w.cross(&v);
+        let v = unit_vector(cross(w, a));
+        let u = cross(w, v);
 
         ONB { u, v, w }
     }
@@ -0,0 +1,107 @@
+use crate::{
+    bsdf::Bsdf,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    material::Scatter,
+    path_sample,
+    ray::Ray,
+    render::Background,
+    utils::{fmax, INFINITY},
+    vec3::{dot, unit_vector, Color, Point3, Vec3},
+};
+
+/// Veach's power heuristic (beta = 2) for combining two sampling strategies.
+pub fn power_heuristic(pdf_f: f64, pdf_g: f64) -> f64 {
+    let f2 = pdf_f * pdf_f;
+    let g2 = pdf_g * pdf_g;
+    if f2 + g2 == 0.0 { 0.0 } else { f2 / (f2 + g2) }
+}
+
+/// Iterative path tracer with next-event estimation.
+///
+/// At every diffuse hit a point on `lights` is sampled and tested with a
+/// shadow ray, and the bounce direction is sampled from the material. Both
+/// strategies can reach an emitter, so each is weighted with the power
+/// heuristic. Emission seen through specular bounces, or when `lights` is
+/// empty, is counted at full weight.
+pub fn path_color<H: Hittable>(
+    r: &Ray,
+    world: &H,
+    lights: &HittableList,
+    background: &Background,
+    max_depth: usize,
+) -> Color {
+    let mut radiance = Color::zero();
+    let mut throughput = Color::ones();
+    let mut ray = *r;
+    let mut prev_p = r.origin;
+    let mut bsdf_pdf = 0.0;
+    let mut specular = true;
+    let sample_lights = !lights.objects.is_empty();
+
+    for depth in 0..max_depth {
+        let mut rec = HitRecord::new();
+        if !world.hit(&ray, 0.001, INFINITY, &mut rec) {
+            radiance += throughput * background.value(&ray);
+            break;
+        }
+
+        let emitted = rec.emitted();
+        if emitted != Color::zero() {
+            let weight = if specular || !sample_lights {
+                1.0
+            } else {
+                power_heuristic(bsdf_pdf, lights.pdf_value(prev_p, ray.direction))
+            };
+            radiance += weight * throughput * emitted;
+        }
+
+        let mut scattered = Ray::new(Point3::zero(), Vec3::zero(), 0.0);
+        let mut attenuation = Color::zero();
+        let flags = match rec.material().scatter(&ray, &rec, &mut attenuation, &mut scattered) {
+            Some(flags) => flags,
+            None => break,
+        };
+
+        if flags.is_specular() {
+            throughput *= attenuation;
+            specular = true;
+            ray = scattered;
+            continue;
+        }
+
+        if sample_lights {
+            let to_light = ray.spawn(rec.p, lights.random(rec.p));
+            let light_pdf = lights.pdf_value(rec.p, to_light.direction);
+            let light_scattering_pdf = rec.material().scattering_pdf(&ray, &rec, &to_light);
+            let mut light_rec = HitRecord::new();
+            if light_pdf > 0.0
+                && light_scattering_pdf > 0.0
+                && world.hit(&to_light, 0.001, INFINITY, &mut light_rec)
+            {
+                let le = light_rec.emitted();
+                let wi = unit_vector(to_light.direction);
+                let f_cos = rec.material().eval(&rec, -unit_vector(ray.direction), wi) * dot(&rec.normal, &wi).abs();
+                let weight = power_heuristic(light_pdf, light_scattering_pdf);
+                radiance += weight * throughput * f_cos * le / light_pdf;
+            }
+        }
+
+        // The attenuation of a sampled bounce is already the BSDF times the
+        // cosine over scattering_pdf.
+        throughput *= attenuation;
+        prev_p = rec.p;
+        bsdf_pdf = rec.material().scattering_pdf(&ray, &rec, &scattered);
+        specular = false;
+        ray = scattered;
+
+        if depth >= 3 {
+            let q = fmax(throughput.x, fmax(throughput.y, throughput.z)).min(0.95);
+            if path_sample!() >= q {
+                break;
+            }
+            throughput = throughput / q;
+        }
+    }
+    radiance
+}
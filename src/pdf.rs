@@ -0,0 +1,110 @@
+use crate::{
+    bsdf::{sample_uniforms, Bsdf},
+    hittable::{HitRecord, Hittable},
+    onb::ONB,
+    random_double,
+    utils::PI,
+    vec3::{dot, random_cosine_direction, unit_vector, Point3, Vec3},
+};
+
+/// A probability density over directions, used to importance sample rays.
+pub trait Pdf {
+    fn value(&self, direction: Vec3) -> f64;
+    fn generate(&self) -> Vec3;
+}
+
+/// Cosine-weighted hemisphere around a normal, matching a Lambertian lobe.
+pub struct CosinePdf {
+    uvw: ONB,
+}
+
+impl CosinePdf {
+    pub fn new(w: Vec3) -> Self {
+        Self { uvw: ONB::build_from_w(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let cosine = dot(&unit_vector(direction), &self.uvw.w);
+        if cosine <= 0.0 { 0.0 } else { cosine / PI }
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.uvw.local(random_cosine_direction())
+    }
+}
+
+/// Directions drawn by the material at `rec` for light leaving along `wo`.
+///
+/// `generate` returns the zero vector when the material absorbs the sample.
+pub struct BsdfPdf<'a> {
+    rec: &'a HitRecord,
+    wo: Vec3,
+}
+
+impl<'a> BsdfPdf<'a> {
+    pub fn new(rec: &'a HitRecord, wo: Vec3) -> Self {
+        Self { rec, wo }
+    }
+}
+
+impl<'a> Pdf for BsdfPdf<'a> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.rec.material().pdf(self.rec, self.wo, unit_vector(direction))
+    }
+
+    fn generate(&self) -> Vec3 {
+        match self.rec.material().sample(self.rec, self.wo, sample_uniforms()) {
+            Some(s) => s.wi,
+            None => Vec3::zero(),
+        }
+    }
+}
+
+/// Directions from `origin` toward the surface of a (light) hittable.
+pub struct HittablePdf<'a> {
+    origin: Point3,
+    hittable: &'a dyn Hittable,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(hittable: &'a dyn Hittable, origin: Point3) -> Self {
+        Self { origin, hittable }
+    }
+}
+
+impl<'a> Pdf for HittablePdf<'a> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.hittable.pdf_value(self.origin, direction)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.hittable.random(self.origin)
+    }
+}
+
+/// Equal-weight mixture of two densities.
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        Self { p: [p0, p1] }
+    }
+}
+
+impl<'a> Pdf for MixturePdf<'a> {
+    fn value(&self, direction: Vec3) -> f64 {
+        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    }
+
+    fn generate(&self) -> Vec3 {
+        if random_double!() < 0.5 {
+            self.p[0].generate()
+        } else {
+            self.p[1].generate()
+        }
+    }
+}
@@ -10,6 +10,12 @@ pub struct Perlin {
     ranvec: Vec<Vec3>,
 }
 
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Perlin {
     fn perlin_generate() -> Vec<Vec3> {
         let mut rng = rand::thread_rng();
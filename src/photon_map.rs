@@ -0,0 +1,330 @@
+use crate::{
+    bsdf::Bsdf,
+    camera::Camera,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    material::Scatter,
+    onb::ONB,
+    random_double,
+    ray::Ray,
+    spectrum::{sample_wavelength, Channels},
+    render::{average_samples, Background, Framebuffer, RenderSettings},
+    utils::{fmax, INFINITY, PI},
+    vec3::{dot, random_cosine_direction, unit_vector, Color, Point3, Vec3},
+};
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+// Radius reduction of progressive photon mapping (Knaus and Zwicker 2011).
+const ALPHA: f64 = 2.0 / 3.0;
+
+/// Flux arriving at `p` travelling along `direction`.
+#[derive(Clone, Copy)]
+pub struct Photon {
+    pub p: Point3,
+    pub direction: Vec3,
+    pub power: Color,
+}
+
+/// Photons stored as an implicit balanced kd-tree: the median of every
+/// subslice is the node splitting it, along the axis kept in `axes`.
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    axes: Vec<u8>,
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        Self::build(&mut photons, &mut axes);
+        Self { photons, axes }
+    }
+
+    fn build(photons: &mut [Photon], axes: &mut [u8]) {
+        if photons.len() <= 1 {
+            return
+        }
+        let mut min = photons[0].p;
+        let mut max = photons[0].p;
+        for ph in photons.iter() {
+            min = Point3::from([min.x.min(ph.p.x), min.y.min(ph.p.y), min.z.min(ph.p.z)]);
+            max = Point3::from([max.x.max(ph.p.x), max.y.max(ph.p.y), max.z.max(ph.p.z)]);
+        }
+        let extent = max - min;
+        let axis = if extent.x > extent.y && extent.x > extent.z { 0 } else if extent.y > extent.z { 1 } else { 2 };
+
+        let mid = photons.len() / 2;
+        photons.select_nth_unstable_by(mid, |a, b| a.p[axis].partial_cmp(&b.p[axis]).unwrap_or(Ordering::Equal));
+        axes[mid] = axis as u8;
+        let (left, right) = photons.split_at_mut(mid);
+        let (left_axes, right_axes) = axes.split_at_mut(mid);
+        Self::build(left, left_axes);
+        Self::build(&mut right[1..], &mut right_axes[1..]);
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Call `f` on every photon within `radius` of `p`.
+    pub fn for_each_within<F: FnMut(&Photon)>(&self, p: Point3, radius: f64, mut f: F) {
+        self.query(0, self.photons.len(), p, radius * radius, &mut f);
+    }
+
+    fn query<F: FnMut(&Photon)>(&self, lo: usize, hi: usize, p: Point3, r2: f64, f: &mut F) {
+        if lo >= hi {
+            return
+        }
+        let mid = lo + (hi - lo) / 2;
+        let photon = &self.photons[mid];
+        if (photon.p - p).length_squared() <= r2 {
+            f(photon);
+        }
+        let d = p[self.axes[mid] as usize] - photon.p[self.axes[mid] as usize];
+        let (near, far) = if d < 0.0 { ((lo, mid), (mid + 1, hi)) } else { ((mid + 1, hi), (lo, mid)) };
+        self.query(near.0, near.1, p, r2, f);
+        if d * d <= r2 {
+            self.query(far.0, far.1, p, r2, f);
+        }
+    }
+}
+
+/// Photons stored at every diffuse hit, and the subset that reached their
+/// diffuse surface through specular bounces only.
+pub struct PhotonMaps {
+    pub global: PhotonMap,
+    pub caustic: PhotonMap,
+}
+
+/// Shoot `count` photons from `lights`, following them through `world` for
+/// at most `max_depth` bounces.
+///
+/// Each light is picked with equal probability and emits from its outward
+/// side with a cosine distribution, so a photon carries
+/// `Le * area * PI * lights / count`.
+pub fn trace_photons<H: Hittable + Sync>(world: &H, lights: &HittableList, count: usize, max_depth: usize) -> PhotonMaps {
+    let (global, caustic) = if lights.objects.is_empty() {
+        (Vec::new(), Vec::new())
+    } else {
+        (0..count)
+            .into_par_iter()
+            .fold(
+                || (Vec::new(), Vec::new()),
+                |(mut global, mut caustic), _| {
+                    trace_photon(world, lights, count, max_depth, &mut global, &mut caustic);
+                    (global, caustic)
+                },
+            )
+            .reduce(
+                || (Vec::new(), Vec::new()),
+                |(mut g0, mut c0), (g1, c1)| {
+                    g0.extend(g1);
+                    c0.extend(c1);
+                    (g0, c0)
+                },
+            )
+    };
+    PhotonMaps { global: PhotonMap::new(global), caustic: PhotonMap::new(caustic) }
+}
+
+fn trace_photon<H: Hittable>(
+    world: &H,
+    lights: &HittableList,
+    count: usize,
+    max_depth: usize,
+    global: &mut Vec<Photon>,
+    caustic: &mut Vec<Photon>,
+) {
+    let n = lights.objects.len();
+    let light = &lights.objects[((random_double!() * n as f64) as usize).min(n - 1)];
+    let area = light.area();
+    if area <= 0.0 {
+        return
+    }
+    let (p, normal) = light.random_point();
+    // Look the emitter up from just outside so textured lights are
+    // evaluated at the right (u, v).
+    let mut light_rec = HitRecord::new();
+    if !light.hit(&Ray::new(p + normal, -normal, 0.0), 0.5, 1.5, &mut light_rec) {
+        return
+    }
+    let mut power = light_rec.emitted()
+        * (area * PI * n as f64 / count as f64);
+    let mut ray = Ray::new(p, ONB::build_from_w(normal).local(random_cosine_direction()), 0.0);
+    ray.channels = Channels::Wavelength(sample_wavelength(random_double!()));
+    let mut specular_path = true;
+
+    for depth in 0..max_depth {
+        let mut rec = HitRecord::new();
+        if !world.hit(&ray, 0.001, INFINITY, &mut rec) {
+            break
+        }
+        let mut scattered = Ray::new(Point3::zero(), Vec3::zero(), 0.0);
+        let mut attenuation = Color::zero();
+        let flags = match rec.material().scatter(&ray, &rec, &mut attenuation, &mut scattered) {
+            Some(flags) => flags,
+            None => break,
+        };
+        if !flags.is_specular() {
+            let photon = Photon { p: rec.p, direction: unit_vector(ray.direction), power };
+            global.push(photon);
+            if specular_path && depth > 0 {
+                caustic.push(photon);
+            }
+            specular_path = false;
+
+            // Photons scatter through the adjoint BSDF, which differs from
+            // the sampled attenuation for rough refraction.
+            let wi = unit_vector(scattered.direction);
+            let scattering_pdf = rec.material().scattering_pdf(&ray, &rec, &scattered);
+            let attenuation = rec.material().eval(&rec, wi, -unit_vector(ray.direction))
+                * (dot(&rec.normal, &wi).abs() / scattering_pdf);
+            let q = fmax(attenuation.x, fmax(attenuation.y, attenuation.z)).min(1.0);
+            if random_double!() >= q {
+                break
+            }
+            power = power * attenuation / q;
+        } else {
+            power *= attenuation;
+        }
+        ray = scattered;
+    }
+}
+
+/// Radiance leaving `rec` toward the origin of `r_in`, from the photons
+/// within `radius`. Photons are traced in RGB and uplifted at a spectral hit.
+fn estimate(map: &PhotonMap, r_in: &Ray, rec: &HitRecord, radius: f64) -> Color {
+    let wo = -unit_vector(r_in.direction);
+    let mut reflected = Color::zero();
+    map.for_each_within(rec.p, radius, |photon| {
+        reflected += rec.uplift(photon.power) * rec.material().eval(rec, wo, -photon.direction);
+    });
+    reflected / (PI * radius * radius)
+}
+
+/// One shadow-ray sample of the light arriving directly from `lights`.
+fn direct_light<H: Hittable>(world: &H, lights: &HittableList, r_in: &Ray, rec: &HitRecord) -> Color {
+    if lights.objects.is_empty() {
+        return Color::zero()
+    }
+    let to_light = r_in.spawn(rec.p, lights.random(rec.p));
+    let light_pdf = lights.pdf_value(rec.p, to_light.direction);
+    let scattering_pdf = rec.material().scattering_pdf(r_in, rec, &to_light);
+    let mut light_rec = HitRecord::new();
+    if light_pdf <= 0.0 || scattering_pdf <= 0.0 || !world.hit(&to_light, 0.001, INFINITY, &mut light_rec) {
+        return Color::zero()
+    }
+    let le = light_rec.emitted();
+    let wi = unit_vector(to_light.direction);
+    let f_cos = rec.material().eval(rec, -unit_vector(r_in.direction), wi) * dot(&rec.normal, &wi).abs();
+    f_cos * le / light_pdf
+}
+
+/// Photon mapping estimate for a camera ray.
+///
+/// The ray is followed through specular bounces to its first diffuse hit,
+/// where direct light is sampled from `lights`, caustics are read from the
+/// caustic map, and one final-gather ray reads the global map at the next
+/// diffuse surface it reaches. Emitters must be in `lights` to shine.
+pub fn photon_color<H: Hittable>(
+    r: &Ray,
+    world: &H,
+    lights: &HittableList,
+    maps: &PhotonMaps,
+    radius: f64,
+    background: &Background,
+    max_depth: usize,
+) -> Color {
+    let mut radiance = Color::zero();
+    let mut throughput = Color::ones();
+    let mut ray = *r;
+
+    for _ in 0..max_depth {
+        let mut rec = HitRecord::new();
+        if !world.hit(&ray, 0.001, INFINITY, &mut rec) {
+            radiance += throughput * background.value(&ray);
+            break
+        }
+        radiance += throughput * rec.emitted();
+
+        let mut scattered = Ray::new(Point3::zero(), Vec3::zero(), 0.0);
+        let mut attenuation = Color::zero();
+        let flags = match rec.material().scatter(&ray, &rec, &mut attenuation, &mut scattered) {
+            Some(flags) => flags,
+            None => break,
+        };
+        if flags.is_specular() {
+            throughput *= attenuation;
+            ray = scattered;
+            continue
+        }
+
+        let direct = direct_light(world, lights, &ray, &rec);
+        let caustic = estimate(&maps.caustic, &ray, &rec, radius);
+        let indirect = attenuation * final_gather(&scattered, world, maps, radius, background, max_depth);
+        radiance += throughput * (direct + caustic + indirect);
+        break
+    }
+    radiance
+}
+
+/// Follow a gather ray through specular bounces and read the global map
+/// where it lands. Emitters it meets are skipped, as direct light and
+/// caustics are estimated separately.
+fn final_gather<H: Hittable>(r: &Ray, world: &H, maps: &PhotonMaps, radius: f64, background: &Background, max_depth: usize) -> Color {
+    let mut throughput = Color::ones();
+    let mut ray = *r;
+    for _ in 0..max_depth {
+        let mut rec = HitRecord::new();
+        if !world.hit(&ray, 0.001, INFINITY, &mut rec) {
+            return throughput * background.value(&ray)
+        }
+        let mut scattered = Ray::new(Point3::zero(), Vec3::zero(), 0.0);
+        let mut attenuation = Color::zero();
+        let flags = match rec.material().scatter(&ray, &rec, &mut attenuation, &mut scattered) {
+            Some(flags) => flags,
+            None => break,
+        };
+        if !flags.is_specular() {
+            return throughput * estimate(&maps.global, &ray, &rec, radius)
+        }
+        throughput *= attenuation;
+        ray = scattered;
+    }
+    Color::zero()
+}
+
+/// Render `passes` independent photon mapping passes and average them.
+///
+/// Every pass traces `photons` fresh photons and shrinks the gather radius
+/// by `r² <- r² (i + ALPHA) / (i + 1)`, so the bias of the average vanishes
+/// as passes are added and glass caustics sharpen instead of staying blurred.
+pub fn render<H: Hittable + Sync>(
+    settings: &RenderSettings,
+    lights: &HittableList,
+    cam: &Camera,
+    world: &H,
+    photons: usize,
+    gather_radius: f64,
+    passes: usize,
+) -> Framebuffer {
+    let mut fb = Framebuffer::new(settings.image_width, settings.image_height);
+    let passes = passes.max(1);
+    let mut radius = gather_radius;
+    for pass in 0..passes {
+        let maps = trace_photons(world, lights, photons, settings.max_depth);
+        let image = average_samples(settings, cam, |r| {
+            photon_color(r, world, lights, &maps, radius, &settings.background, settings.max_depth)
+        });
+        for (sum, c) in fb.data.iter_mut().zip(image) {
+            *sum += c / passes as f64;
+        }
+        let i = (pass + 1) as f64;
+        radius *= ((i + ALPHA) / (i + 1.0)).sqrt();
+    }
+    fb
+}
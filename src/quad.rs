@@ -0,0 +1,109 @@
+use crate::{
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    path_sample,
+    ray::Ray,
+    vec3::{cross, dot, unit_vector, Point3, Vec3},
+    aabb::Aabb,
+    utils::{INFINITY, fmax, fmin}
+};
+use std::sync::Arc;
+
+/// Parallelogram spanned by `u` and `v` from corner `q`.
+#[derive(Clone)]
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    w: Vec3,
+    d: f64,
+    area: f64,
+    material: Arc<Material>,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, material: impl Into<Arc<Material>>) -> Self {
+        let n = cross(u, v);
+        let normal = unit_vector(n);
+        Self {
+            q,
+            u,
+            v,
+            normal,
+            w: n / dot(&n, &n),
+            d: dot(&normal, &q),
+            area: n.length(),
+            material: material.into(),
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let denom = dot(&self.normal, &r.direction);
+        if denom.abs() < 1e-8 {
+            return false
+        }
+        let t = (self.d - dot(&self.normal, &r.origin)) / denom;
+        if t < t_min || t > t_max {
+            return false
+        }
+
+        let planar = r.at(t) - self.q;
+        let alpha = dot(&self.w, &cross(planar, self.v));
+        let beta = dot(&self.w, &cross(self.u, planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return false
+        }
+        if !self.material.opaque(alpha, beta, &r.at(t)) {
+            return false
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.dpdu = self.u;
+        rec.dpdv = self.v;
+        rec.set_face_normal(r, &self.normal);
+        rec.set_material(&self.material);
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        let corners = [self.q, self.q + self.u, self.q + self.v, self.q + self.u + self.v];
+        let mut small = corners[0];
+        let mut big = corners[0];
+        for c in corners.iter() {
+            small = Point3::from([fmin(small.x, c.x), fmin(small.y, c.y), fmin(small.z, c.z)]);
+            big = Point3::from([fmax(big.x, c.x), fmax(big.y, c.y), fmax(big.z, c.z)]);
+        }
+        // Pad flat boxes so the slab test never sees a zero-width axis.
+        let pad = Vec3::from([0.0001, 0.0001, 0.0001]);
+        output_box.modify(small - pad, big + pad);
+        true
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction, 0.0), 0.001, INFINITY, &mut rec) {
+            return 0.0
+        }
+        let distance_squared = rec.t.powi(2) * direction.length_squared();
+        let cosine = (dot(&direction, &rec.normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.random_point().0 - origin
+    }
+
+    fn area(&self) -> f64 {
+        self.area
+    }
+
+    fn random_point(&self) -> (Point3, Vec3) {
+        (self.q + path_sample!() * self.u + path_sample!() * self.v, self.normal)
+    }
+}
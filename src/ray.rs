@@ -1,9 +1,28 @@
-use crate::vec3::{Point3, Vec3};
+use crate::{
+    spectrum::{wavelength_weight, Channels},
+    vec3::{Color, Point3, Vec3},
+};
 
+/// Progress of a random walk through a subsurface material: the channel
+/// whose extinction samples distances, and the density of the walk so far
+/// under each channel's, relative to their mean.
+#[derive(Clone, Copy, Debug)]
+pub struct MediumWalk {
+    pub(crate) channel: usize,
+    pub(crate) pdf: Color,
+}
+
+#[derive(Clone, Copy)]
 pub struct Ray {
     pub origin: Point3,
     pub direction: Vec3,
     pub time: f64,
+    /// RGB, or the wavelengths in nanometres the path has sampled.
+    pub channels: Channels,
+    /// Whether a dispersive bounce has tied the path to its wavelength.
+    pub dispersed: bool,
+    /// Random walk the ray is on inside a subsurface material.
+    pub walk: Option<MediumWalk>,
 }
 
 impl Ray {
@@ -12,9 +31,42 @@ impl Ray {
             origin,
             direction,
             time,
+            channels: Channels::Rgb,
+            dispersed: false,
+            walk: None,
+        }
+    }
+
+    /// Factor for the first dispersive bounce of the path. An RGB path takes
+    /// on the color of its wavelength; a spectral path drops all but the
+    /// hero wavelength, which then stands for the three.
+    pub fn dispersion_weight(&self) -> Color {
+        match self.channels {
+            Channels::Rgb => Color::ones(),
+            Channels::Wavelength(lambda) => wavelength_weight(lambda),
+            Channels::Spectrum(_) => Color::from([3.0, 0.0, 0.0]),
+        }
+    }
+
+    /// RGB color `c` in the units this ray carries.
+    pub fn uplift(&self, c: Color) -> Color {
+        self.channels.uplift(c)
+    }
+
+    /// What radiance `l` arriving along this ray adds to the film: CIE XYZ
+    /// for spectral rays, RGB otherwise.
+    pub fn film(&self, l: Color) -> Color {
+        match self.channels {
+            Channels::Spectrum(w) => w.to_xyz(l),
+            _ => l,
         }
     }
 
+    /// Next segment of the same path, keeping the time and wavelength.
+    pub fn spawn(&self, origin: Point3, direction: Vec3) -> Self {
+        Self { origin, direction, ..*self }
+    }
+
     pub fn origin(&self) -> Point3 {
         self.origin
     }
@@ -0,0 +1,288 @@
+use crate::{
+    bdpt,
+    bsdf::Bsdf,
+    camera::Camera,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    material::Scatter,
+    mlt,
+    photon_map,
+    path_tracer::path_color,
+    path_sample,
+    pdf::{BsdfPdf, HittablePdf, MixturePdf, Pdf},
+    random_double,
+    ray::Ray,
+    spectrum::{sample_wavelength, xyz_to_film, Channels, Wavelengths},
+    utils::{clamp, INFINITY},
+    vec3::{dot, unit_vector, Color, Point3, Vec3},
+};
+use itertools::iproduct;
+use rayon::prelude::*;
+use std::io::{self, Write};
+
+/// What a ray sees when it leaves the scene without hitting anything.
+#[derive(Clone, Copy)]
+pub enum Background {
+    /// White-to-blue sky, blended by `scale * (dir.y + offset)`.
+    Gradient { scale: f64, offset: f64 },
+    /// Constant radiance, e.g. black for closed, self-lit interiors.
+    Solid(Color),
+}
+
+impl Background {
+    pub fn sky() -> Self {
+        Background::Gradient { scale: 0.5, offset: 1.0 }
+    }
+
+    pub fn value(&self, r: &Ray) -> Color {
+        r.uplift(match *self {
+            Background::Gradient { scale, offset } => {
+                let unit_direction = unit_vector(r.direction);
+                let t = scale * (unit_direction.y + offset);
+                (1.0 - t) * Color::ones() + t * Color::new(0.5, 0.7, 1.0)
+            }
+            Background::Solid(c) => c,
+        })
+    }
+}
+
+/// Light transport algorithm used to estimate each camera sample.
+#[derive(Clone, Copy)]
+pub enum Integrator {
+    /// Recursive `ray_color`, mixing light and cosine sampling at diffuse hits.
+    Recursive,
+    /// Iterative path tracer with next-event estimation and MIS.
+    PathTracer,
+    /// Primary sample space MLT over the path tracer. Runs
+    /// `mutations_per_pixel * pixels` mutations in total, of which roughly
+    /// `large_step_probability` are independent restarts, and ignores
+    /// `samples_per_pixel`.
+    Metropolis { mutations_per_pixel: usize, large_step_probability: f64 },
+    /// Photon mapping with final gather, emitting `photons` from `lights` in
+    /// each of `passes` progressive passes. The gather radius starts at
+    /// `gather_radius` and shrinks from pass to pass.
+    PhotonMapping { photons: usize, gather_radius: f64, passes: usize },
+    /// Bidirectional path tracer joining camera and light subpaths with MIS.
+    /// Needs the emitters in `lights`.
+    Bidirectional,
+}
+
+#[derive(Clone, Copy)]
+pub struct RenderSettings {
+    pub image_width: u32,
+    pub image_height: u32,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    pub background: Background,
+    pub integrator: Integrator,
+    /// Trace sampled wavelengths instead of RGB, uplifting the scene's
+    /// colors to spectra and accumulating CIE XYZ.
+    pub spectral: bool,
+}
+
+impl RenderSettings {
+    pub fn new(image_width: u32, aspect_ratio: f64, samples_per_pixel: usize, max_depth: usize) -> Self {
+        Self {
+            image_width,
+            image_height: (image_width as f64 / aspect_ratio) as u32,
+            samples_per_pixel,
+            max_depth,
+            background: Background::sky(),
+            integrator: Integrator::Recursive,
+            spectral: false,
+        }
+    }
+}
+
+pub struct Pixel {
+    pub r: u32,
+    pub g: u32,
+    pub b: u32,
+}
+
+impl Pixel {
+    /// Gamma-2 encode a linear color into 8-bit channels.
+    pub fn from_color(c: Color) -> Self {
+        let get_color = |c: f64| (255.999 * clamp(f64::sqrt(c), 0.0, 0.999)) as u32;
+        Pixel {
+            r: get_color(c.x),
+            g: get_color(c.y),
+            b: get_color(c.z),
+        }
+    }
+}
+
+/// Linear radiance per pixel, averaged over samples, top row first.
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![Color::zero(); (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, row: u32, col: u32) -> Color {
+        self.data[(row * self.width + col) as usize]
+    }
+
+    pub fn pixels(&self) -> Vec<Pixel> {
+        self.data.iter().map(|c| Pixel::from_color(*c)).collect()
+    }
+
+    pub fn write_ppm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {}", self.width, self.height)?;
+        writeln!(out, "255")?;
+        for it in self.pixels() {
+            writeln!(out, "{} {} {}", it.r, it.g, it.b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recursive path tracer. Smooth bounces are importance sampled from a
+/// mixture of the material and `lights`, or the material alone when
+/// `lights` is empty.
+pub fn ray_color<H: Hittable>(
+    r: &Ray,
+    world: &H,
+    lights: &HittableList,
+    background: &Background,
+    depth: usize,
+) -> Color {
+    let mut rec = HitRecord::new();
+    if depth == 0 {
+        return Color::zero();
+    }
+    if !world.hit(r, 0.001, INFINITY, &mut rec) {
+        return background.value(r);
+    }
+
+    let mut scattered = Ray::new(Point3::zero(), Vec3::zero(), 0.0);
+    let mut attenuation = Color::zero();
+    let emitted = rec.emitted();
+    let flags = match rec.material().scatter(r, &rec, &mut attenuation, &mut scattered) {
+        Some(flags) => flags,
+        None => return emitted,
+    };
+    if lights.objects.is_empty() || rec.material().is_delta() {
+        return emitted + attenuation * ray_color(&scattered, world, lights, background, depth - 1);
+    }
+
+    // Half the time the sampled bounce gives way to a direction toward the
+    // lights. Delta lobes are only reached by the bounce, so they count
+    // twice.
+    let wo = -unit_vector(r.direction);
+    let bsdf_pdf = BsdfPdf::new(&rec, wo);
+    let light_pdf = HittablePdf::new(lights, rec.p);
+    let mixture_pdf = MixturePdf::new(&light_pdf, &bsdf_pdf);
+    if random_double!() < 0.5 {
+        scattered = r.spawn(rec.p, light_pdf.generate());
+    } else if flags.is_specular() {
+        return emitted + 2.0 * attenuation * ray_color(&scattered, world, lights, background, depth - 1);
+    }
+    let pdf_val = mixture_pdf.value(scattered.direction);
+    if pdf_val <= 0.0 {
+        return emitted;
+    }
+    let wi = unit_vector(scattered.direction);
+    let f_cos = rec.material().eval(&rec, wo, wi) * dot(&rec.normal, &wi).abs();
+    emitted + f_cos * ray_color(&scattered, world, lights, background, depth - 1) / pdf_val
+}
+
+pub struct Renderer {
+    pub settings: RenderSettings,
+    /// Emitters to sample directly at diffuse hits; leave empty for sky-lit scenes.
+    pub lights: HittableList,
+}
+
+impl Renderer {
+    pub fn new(settings: RenderSettings) -> Self {
+        Self {
+            settings,
+            lights: HittableList::default(),
+        }
+    }
+
+    pub fn render<H: Hittable + Sync>(&self, cam: &Camera, world: &H) -> Framebuffer {
+        let s = &self.settings;
+        match s.integrator {
+            Integrator::Metropolis { mutations_per_pixel, large_step_probability } => {
+                return mlt::render(s, &self.lights, cam, world, mutations_per_pixel, large_step_probability)
+            }
+            Integrator::PhotonMapping { photons, gather_radius, passes } => {
+                return photon_map::render(s, &self.lights, cam, world, photons, gather_radius, passes)
+            }
+            Integrator::Bidirectional => return bdpt::render(s, &self.lights, cam, world),
+            _ => {}
+        }
+        let mut fb = Framebuffer::new(s.image_width, s.image_height);
+        fb.data = average_samples(s, cam, |r| match s.integrator {
+            Integrator::Recursive => ray_color(r, world, &self.lights, &s.background, s.max_depth),
+            _ => path_color(r, world, &self.lights, &s.background, s.max_depth),
+        });
+        fb
+    }
+}
+
+/// Camera ray through `(u, v)`, carrying sampled wavelengths in spectral mode
+/// and a single wavelength for dispersive materials otherwise.
+pub(crate) fn camera_ray(s: &RenderSettings, cam: &Camera, u: f64, v: f64) -> Ray {
+    let mut ray = cam.get_ray(u, v);
+    if s.spectral {
+        ray.channels = Channels::Spectrum(Wavelengths::sample(path_sample!()));
+    } else {
+        ray.channels = Channels::Wavelength(sample_wavelength(path_sample!()));
+    }
+    ray
+}
+
+/// Film coordinates of the point `(x, y)` pixels from the bottom-left
+/// corner of the image, so that the image spans the film exactly.
+pub(crate) fn film_position(s: &RenderSettings, x: f64, y: f64) -> (f64, f64) {
+    (x / s.image_width as f64, y / s.image_height as f64)
+}
+
+/// Framebuffer index of the pixel that film coordinates `(u, v)` fall in,
+/// the inverse of `film_position`. Framebuffer rows run top to bottom,
+/// film `v` bottom to top.
+pub(crate) fn film_pixel(s: &RenderSettings, u: f64, v: f64) -> usize {
+    let (w, h) = (s.image_width as usize, s.image_height as usize);
+    let col = ((u * w as f64) as usize).min(w - 1);
+    let row = h - 1 - ((v * h as f64) as usize).min(h - 1);
+    row * w + col
+}
+
+/// Film color of a pixel accumulated with `Ray::film`.
+pub(crate) fn film_to_rgb(s: &RenderSettings, c: Color) -> Color {
+    if s.spectral { xyz_to_film(c) } else { c }
+}
+
+/// Average `radiance` over `samples_per_pixel` jittered camera rays per
+/// pixel, in framebuffer order.
+pub(crate) fn average_samples<F>(s: &RenderSettings, cam: &Camera, radiance: F) -> Vec<Color>
+where
+    F: Fn(&Ray) -> Color + Sync,
+{
+    let pix_coord: Vec<(u32, u32)> = iproduct!((0..s.image_height).rev(), 0..s.image_width).collect();
+    pix_coord
+        .par_iter()
+        .map(|&(row, col)| {
+            let pixel_color = (1..=s.samples_per_pixel)
+                .map(|_| {
+                    let (u, v) = film_position(s, col as f64 + random_double!(), row as f64 + random_double!());
+                    let ray = camera_ray(s, cam, u, v);
+                    ray.film(radiance(&ray))
+                })
+                .fold(Color::default(), |sum, c| sum + c);
+            film_to_rgb(s, pixel_color / s.samples_per_pixel as f64)
+        })
+        .collect()
+}
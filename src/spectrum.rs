@@ -0,0 +1,173 @@
+use crate::vec3::{Color, Vec3};
+
+// Colors are RGB by default. A spectral path instead carries three sampled
+// wavelengths, each channel of its colors holding the value at one of them;
+// RGB inputs are uplifted to spectra where they enter, and the path's
+// radiance is turned into CIE XYZ at the film.
+
+/// Range of wavelengths, in nanometres, that rays are sampled from.
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+
+// Integral of `xyz_to_rgb(cie_xyz(lambda))` over the sampled range, so an
+// equal-energy spectrum comes out white.
+const WHITE: [f64; 3] = [128.335162, 101.543786, 97.116889];
+
+/// CIE 1931 2° colour matching functions, after the multi-lobe Gaussian fit
+/// of Wyman, Sloan and Shirley (2013).
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    let g = |mu: f64, below: f64, above: f64| {
+        let t = (lambda - mu) / if lambda < mu { below } else { above };
+        (-0.5 * t * t).exp()
+    };
+    Vec3::from([
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    ])
+}
+
+/// Linear sRGB (D65 primaries) from CIE XYZ.
+pub fn xyz_to_rgb(xyz: Vec3) -> Color {
+    Color::from([
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    ])
+}
+
+/// Wavelength drawn from a uniform number, concentrated where the eye is
+/// most sensitive (Radziszewski et al. 2009).
+pub fn sample_wavelength(u: f64) -> f64 {
+    538.0 - 138.888889 * (0.85691062 - 1.82750197 * u).atanh()
+}
+
+/// Density of `sample_wavelength`, per nanometre.
+pub fn wavelength_pdf(lambda: f64) -> f64 {
+    if !(LAMBDA_MIN..=LAMBDA_MAX).contains(&lambda) {
+        return 0.0
+    }
+    0.0039398042 / (0.0072 * (lambda - 538.0)).cosh().powi(2)
+}
+
+/// Film RGB of CIE XYZ accumulated from sampled wavelengths, balanced so
+/// that an equal-energy spectrum is white.
+pub fn xyz_to_film(xyz: Vec3) -> Color {
+    xyz_to_rgb(xyz) / Color::from(WHITE)
+}
+
+/// RGB seen through a path restricted to `lambda`, as drawn by
+/// `sample_wavelength`. Averages to white over wavelengths, but single
+/// channels may be negative outside the sRGB gamut.
+pub fn wavelength_weight(lambda: f64) -> Color {
+    xyz_to_film(cie_xyz(lambda) / wavelength_pdf(lambda))
+}
+
+/// Wavelengths carried by a spectral path, one per channel of its colors.
+#[derive(Clone, Copy, Debug)]
+pub struct Wavelengths {
+    pub lambda: [f64; 3],
+}
+
+impl Wavelengths {
+    /// Three wavelengths stratified from one uniform number; the first is
+    /// the hero wavelength that dispersive materials refract.
+    pub fn sample(u: f64) -> Self {
+        let lambda = |k: f64| sample_wavelength((u + k / 3.0).fract());
+        Self { lambda: [lambda(0.0), lambda(1.0), lambda(2.0)] }
+    }
+
+    /// Values at these wavelengths of a smooth spectrum with color `rgb`.
+    pub fn uplift(&self, rgb: Color) -> Color {
+        Color::from([smits(rgb, self.lambda[0]), smits(rgb, self.lambda[1]), smits(rgb, self.lambda[2])])
+    }
+
+    /// CIE XYZ estimate from radiance `l` sampled at these wavelengths.
+    pub fn to_xyz(&self, l: Color) -> Vec3 {
+        let xyz = |k: usize| cie_xyz(self.lambda[k]) * (l[k] / wavelength_pdf(self.lambda[k]));
+        (xyz(0) + xyz(1) + xyz(2)) / 3.0
+    }
+}
+
+/// What the color channels of a path stand for.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Channels {
+    /// Plain RGB, refracting like light of the reference wavelength.
+    #[default]
+    Rgb,
+    /// RGB, with one sampled wavelength for dispersive materials to refract.
+    Wavelength(f64),
+    /// One wavelength per channel; dispersive materials refract the first,
+    /// the hero wavelength.
+    Spectrum(Wavelengths),
+}
+
+impl Channels {
+    /// Wavelength that dispersive materials refract, if the path has one.
+    pub fn wavelength(&self) -> Option<f64> {
+        match self {
+            Channels::Rgb => None,
+            Channels::Wavelength(lambda) => Some(*lambda),
+            Channels::Spectrum(w) => Some(w.lambda[0]),
+        }
+    }
+
+    /// Wavelengths of a spectral path; None for RGB.
+    pub fn spectrum(&self) -> Option<Wavelengths> {
+        match self {
+            Channels::Spectrum(w) => Some(*w),
+            _ => None,
+        }
+    }
+
+    /// RGB color `c` in these channels.
+    pub fn uplift(&self, c: Color) -> Color {
+        match self {
+            Channels::Spectrum(w) => w.uplift(c),
+            _ => c,
+        }
+    }
+}
+
+// Smits (1999) basis spectra, in ten bins over 380 to 720 nm.
+const SMITS_WHITE: [f64; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const SMITS_CYAN: [f64; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const SMITS_MAGENTA: [f64; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const SMITS_YELLOW: [f64; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [f64; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [f64; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const SMITS_BLUE: [f64; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+/// RGB to spectrum conversion of Smits (1999): the color is split into
+/// white plus one secondary and one primary, and their basis spectra summed.
+/// Linear in the brightness of `rgb`, so it also suits emission.
+fn smits(rgb: Color, lambda: f64) -> f64 {
+    // Linear between bin centres, constant beyond the outer ones.
+    let x = ((lambda - 380.0) / 34.0 - 0.5).clamp(0.0, 9.0);
+    let i = (x as usize).min(8);
+    let f = x - i as f64;
+    let basis = |s: &[f64; 10]| s[i] * (1.0 - f) + s[i + 1] * f;
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+    if r <= g && r <= b {
+        r * basis(&SMITS_WHITE)
+            + if g <= b {
+                (g - r) * basis(&SMITS_CYAN) + (b - g) * basis(&SMITS_BLUE)
+            } else {
+                (b - r) * basis(&SMITS_CYAN) + (g - b) * basis(&SMITS_GREEN)
+            }
+    } else if g <= r && g <= b {
+        g * basis(&SMITS_WHITE)
+            + if r <= b {
+                (r - g) * basis(&SMITS_MAGENTA) + (b - r) * basis(&SMITS_BLUE)
+            } else {
+                (b - g) * basis(&SMITS_MAGENTA) + (r - b) * basis(&SMITS_RED)
+            }
+    } else {
+        b * basis(&SMITS_WHITE)
+            + if r <= g {
+                (r - b) * basis(&SMITS_YELLOW) + (g - r) * basis(&SMITS_GREEN)
+            } else {
+                (g - b) * basis(&SMITS_YELLOW) + (r - g) * basis(&SMITS_RED)
+            }
+    }
+}
@@ -1,26 +1,30 @@
 use crate::{
+    bsdf::sphere_direction,
     hittable::{HitRecord, Hittable},
     material::Material,
+    path_sample,
     ray::Ray,
-    vec3::{dot, Point3},
-    aabb::Aabb
+    onb::ONB,
+    vec3::{dot, random_unit_vector, Point3, Vec3},
+    aabb::Aabb,
+    utils::INFINITY
 };
+use std::sync::Arc;
 use std::f64::consts::PI;
-use std::f64::INFINITY;
 
 #[derive(Clone)]
 pub struct Sphere {
     center: Point3,
     radius: f64,
-    material: Material,
+    material: Arc<Material>,
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f64, material: Material) -> Self {
+    pub fn new(center: Point3, radius: f64, material: impl Into<Arc<Material>>) -> Self {
         Self {
             center,
             radius,
-            material,
+            material: material.into(),
         }
     }
 
@@ -30,6 +34,19 @@ impl Sphere {
         *u = 1.0 - (phi + PI) / (2.0 * PI);
         *v = (theta + PI / 2.0) / PI;
     }
+
+    /// Derivatives of the point with outward normal `n` on a sphere of
+    /// `radius` along the `(u, v)` of `get_sphere_uv`. `dpdv` vanishes at
+    /// the poles.
+    pub(crate) fn get_sphere_tangents(n: &Vec3, radius: f64) -> (Vec3, Vec3) {
+        let cos_theta = (n.x * n.x + n.z * n.z).sqrt();
+        let dpdu = 2.0 * PI * radius * Vec3::new(n.z, 0.0, -n.x);
+        if cos_theta < 1e-12 {
+            return (dpdu, Vec3::zero())
+        }
+        let dpdv = PI * radius * Vec3::new(-n.y * n.x / cos_theta, cos_theta, -n.y * n.z / cos_theta);
+        (dpdu, dpdv)
+    }
 }
 
 impl Hittable for Sphere {
@@ -44,37 +61,71 @@ impl Hittable for Sphere {
             return false;
         }
         let root = f64::sqrt(discriminant);
-        let mut temp = (-half_b - root) / a;
-        if temp > t_max || temp < t_min {
-            temp = (-half_b + root) / a;
+        // The near root, then the far one, unless an alpha mask cuts them out.
+        for temp in [(-half_b - root) / a, (-half_b + root) / a] {
             if temp > t_max || temp < t_min {
-                return false;
+                continue;
+            }
+            let p = r.at(temp);
+            let outward_normal = (p - self.center) / self.radius;
+            let (mut u, mut v) = (0.0, 0.0);
+            Sphere::get_sphere_uv(&outward_normal, &mut u, &mut v);
+            if !self.material.opaque(u, v, &p) {
+                continue;
             }
-        }
 
-        rec.t = temp;
-        rec.p = r.at(rec.t);
-        let outward_normal = (rec.p - self.center) / self.radius;
-        rec.set_face_normal(r, &outward_normal);
-        Sphere::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
-        rec.material = self.material.clone();
-        return true;
+            rec.t = temp;
+            rec.p = p;
+            rec.set_face_normal(r, &outward_normal);
+            rec.u = u;
+            rec.v = v;
+            (rec.dpdu, rec.dpdv) = Sphere::get_sphere_tangents(&outward_normal, self.radius);
+            rec.set_material(&self.material);
+            return true;
+        }
+        false
     }
 
     fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
         let r = Point3::from([self.radius, self.radius, self.radius]);
         output_box.modify(self.center-r, self.center+r);
-        return true
+        true
     }
 
-    fn pdf_value(&self, origin: Point3, direction: Point3, time: f64) -> f64 {
-        let mut rec = HitRecord::new(Material::new_lambertian(Point3::zero()));
-        if !self.hit(&Ray::new(origin, direction, time), 0.001, INFINITY, &mut rec) {
-            0.0
-        } else {
-            let cos_theta_max = (1 - self.radius * self.radius / (origin - self.center).length_squared()).sqrt();
-            return 1 / (2.0 * PI * (1 - cos_theta_max))
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction, 0.0), 0.001, INFINITY, &mut rec) {
+            return 0.0
+        }
+        let distance_squared = (self.center - origin).length_squared();
+        // From inside, the sphere fills every direction.
+        if distance_squared <= self.radius.powi(2) {
+            return 1.0 / (4.0 * PI)
         }
+        let cos_theta_max = (1.0 - self.radius.powi(2) / distance_squared).sqrt();
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
     }
 
+    fn random(&self, origin: Point3) -> Vec3 {
+        let direction = self.center - origin;
+        let (r1, r2) = (path_sample!(), path_sample!());
+        if direction.length_squared() <= self.radius.powi(2) {
+            return sphere_direction(r1, r2)
+        }
+        // Uniform over the cone of directions the sphere subtends.
+        let cos_theta_max = (1.0 - self.radius.powi(2) / direction.length_squared()).sqrt();
+        let z = 1.0 + r2 * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * r1;
+        let s = (1.0 - z * z).sqrt();
+        ONB::build_from_w(direction).local(Vec3::from([s * phi.cos(), s * phi.sin(), z]))
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn random_point(&self) -> (Point3, Vec3) {
+        let n = random_unit_vector();
+        (self.center + self.radius * n, n)
+    }
 }
@@ -2,9 +2,11 @@ use crate::{
     hittable::{HitRecord, Hittable},
     material::Material,
     ray::Ray,
+    sphere::Sphere,
     vec3::{dot, Point3, align_min, align_max},
     aabb::Aabb
 };
+use std::sync::Arc;
 use std::f64::consts::PI;
 
 #[derive(Clone)]
@@ -12,18 +14,18 @@ pub struct SphereBlur {
     center1: Point3,
     center2: Point3,
     radius: f64,
-    material: Material,
+    material: Arc<Material>,
     time1: f64,
     time2: f64,
 }
 
 impl SphereBlur {
-    pub fn new(center1: Point3, center2: Point3, radius: f64, material: Material, time1: f64, time2: f64,) -> Self {
+    pub fn new(center1: Point3, center2: Point3, radius: f64, material: impl Into<Arc<Material>>, time1: f64, time2: f64,) -> Self {
         Self {
             center1,
             center2,
             radius,
-            material,
+            material: material.into(),
             time1,
             time2,
         }
@@ -53,26 +55,34 @@ impl Hittable for SphereBlur {
             return false;
         }
         let root = f64::sqrt(discriminant);
-        let mut temp = (-half_b - root) / a;
-        if temp > t_max || temp < t_min {
-            temp = (-half_b + root) / a;
+        // The near root, then the far one, unless an alpha mask cuts them out.
+        for temp in [(-half_b - root) / a, (-half_b + root) / a] {
             if temp > t_max || temp < t_min {
-                return false;
+                continue;
+            }
+            let p = r.at(temp);
+            let outward_normal = (p - self.get_center(r.time)) / self.radius;
+            let (mut u, mut v) = (0.0, 0.0);
+            SphereBlur::get_sphere_uv(&outward_normal, &mut u, &mut v);
+            if !self.material.opaque(u, v, &p) {
+                continue;
             }
-        }
 
-        rec.t = temp;
-        rec.p = r.at(rec.t);
-        let outward_normal = (rec.p - self.get_center(r.time)) / self.radius;
-        rec.set_face_normal(r, &outward_normal);
-        SphereBlur::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
-        rec.material = self.material.clone();
-        return true;
+            rec.t = temp;
+            rec.p = p;
+            rec.set_face_normal(r, &outward_normal);
+            rec.u = u;
+            rec.v = v;
+            (rec.dpdu, rec.dpdv) = Sphere::get_sphere_tangents(&outward_normal, self.radius);
+            rec.set_material(&self.material);
+            return true;
+        }
+        false
     }
 
     fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
         let r = Point3::from([self.radius, self.radius, self.radius]);
         output_box.modify(align_min(self.center1, self.center2)-r, align_max(self.center1, self.center2)+r);
-        return true
+        true
     }
 }
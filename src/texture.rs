@@ -1,12 +1,12 @@
 use crate::{
-    random_double,
     perlin::*,
-    vec3::{Vec3, dot, random_in_unit_sphere, random_unit_vector, reflect, refract, unit_vector, Color},
+    vec3::{cross, dot, unit_vector, Vec3, Color},
     utils::clamp,
 };
 
 use image::*;
 use std::path::*;
+use std::sync::Arc;
 
 pub trait Texture {
     fn value(&self, u:f64, v:f64, p:&Vec3) -> Color;
@@ -22,9 +22,19 @@ pub enum Surface {
 
 impl Surface {
     pub fn new_solid_color(c:Color) -> Self {Surface::SolidColor(SolidColor::new(c))}
+    pub fn new_constant(v:f64) -> Self {Surface::SolidColor(SolidColor::new2(v,v,v))}
     pub fn new_test_texture(c:Color) -> Self {Surface::TestTexture(TestTexture::new(c))}
     pub fn new_image_texture(c:&str) -> Self {Surface::ImageTexture(ImageTexture::new_by_pathstr(c))}
     pub fn new_noise_texture(c:f64) -> Self {Surface::NoiseTexture(NoiseTexture::new(c))}
+
+    /// Opacity at `(u, v)`: the alpha channel of images, and the red
+    /// channel of other textures.
+    pub fn opacity(&self, u:f64, v:f64, p:&Vec3) -> f64 {
+        match self {
+            Surface::ImageTexture(r) => r.alpha(u,v),
+            _ => self.value(u,v,p).x,
+        }
+    }
 }
 
 impl Texture for Surface {
@@ -61,7 +71,7 @@ impl SolidColor{
     }
 }
 impl Texture for SolidColor {
-    fn value(&self,u:f64, v:f64,p:&Vec3) -> Vec3{
+    fn value(&self,_u:f64, _v:f64,_p:&Vec3) -> Vec3{
         self.color_value
     }
 }
@@ -88,36 +98,41 @@ impl TestTexture {
 }
 
 impl Texture for TestTexture {
-    fn value(&self, u:f64, v:f64, p:&Vec3) -> Vec3 {
+    fn value(&self, _u:f64, _v:f64, p:&Vec3) -> Vec3 {
         let sines = (10.0 * p.x).sin() * (10.0 * p.y).sin() * (10.0 * p.z).sin();
         if sines < 0.0 {
-            return self.color1;
+            self.color1
         } else {
-            return self.color2;
+            self.color2
         }
     }
 }
 
+/// Image shared by every texture loaded from it, however often the
+/// materials holding it are cloned.
 #[derive(Clone)]
-
 pub struct ImageTexture {
-    pub data: ImageBuffer<image::Rgb<u8>, std::vec::Vec<u8>>,
+    pub data: Arc<ImageBuffer<image::Rgba<u8>, std::vec::Vec<u8>>>,
 }
 impl ImageTexture {
     pub fn new_by_pathstr(dir: &str) -> Self {
-        return Self {
-            data: image::open(&Path::new(dir)).unwrap().to_rgb(),
-        };
+        Self {
+            data: Arc::new(image::open(Path::new(dir)).unwrap().to_rgba8()),
+        }
     }
     pub fn width(&self) -> u32 {
-        return self.data.width();
+        self.data.width()
     }
     pub fn height(&self) -> u32 {
-        return self.data.height();
+        self.data.height()
     }
-}
-impl Texture for ImageTexture {
-    fn value(&self, u: f64, v: f64, _p: &Vec3) -> Vec3 {
+
+    /// Opacity at `(u, v)`, one where the image has no alpha channel.
+    pub fn alpha(&self, u: f64, v: f64) -> f64 {
+        self.texel(u, v)[3] as f64 / 255.0
+    }
+
+    fn texel(&self, u: f64, v: f64) -> [u8; 4] {
         let u = clamp(u, 0.0, 1.0);
         let v = 1.0 - clamp(v, 0.0, 1.0);
         let mut i: u32 = (u * self.width() as f64) as u32;
@@ -130,15 +145,68 @@ impl Texture for ImageTexture {
         if j >= self.height() {
             j = self.height() - 1;
         }
-
+        self.data.get_pixel(i, j).0
+    }
+}
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Vec3) -> Vec3 {
         const COLOR_SCALE: f64 = 1.0 / 255.0;
-        let pixel = self.data.get_pixel(i, j);
-        let [red, green, blue] = pixel.0;
-        return Vec3::new(
+        let [red, green, blue, _] = self.texel(u, v);
+        Vec3::new(
             red as f64 * COLOR_SCALE,
             green as f64 * COLOR_SCALE,
             blue as f64 * COLOR_SCALE,
-        );
+        )
+    }
+}
+
+/// Image that bends the shading normal of a surface, using the tangents
+/// `dpdu` and `dpdv` of its `(u, v)` parametrisation.
+#[derive(Clone)]
+pub enum NormalMap {
+    /// Tangent-space normals stored as `(n + 1) / 2`, with red along `dpdu`,
+    /// green along `dpdv` and blue out of the surface.
+    Normal(ImageTexture),
+    /// Heights from the red channel, in units of `scale`, displacing the
+    /// surface along its normal.
+    Bump { height: ImageTexture, scale: f64 },
+}
+
+impl NormalMap {
+    pub fn new_normal_map(path: &str) -> Self {
+        NormalMap::Normal(ImageTexture::new_by_pathstr(path))
+    }
+
+    pub fn new_bump_map(path: &str, scale: f64) -> Self {
+        NormalMap::Bump { height: ImageTexture::new_by_pathstr(path), scale }
+    }
+
+    /// Shading normal replacing the outward normal `n` at `(u, v)`. Surfaces
+    /// without tangents keep `n`.
+    pub fn shading_normal(&self, n: Vec3, dpdu: Vec3, dpdv: Vec3, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        let tangent = dpdu - n * dot(&n, &dpdu);
+        if tangent.length_squared() < 1e-16 || dpdv.length_squared() < 1e-16 {
+            return n
+        }
+        match self {
+            NormalMap::Normal(image) => {
+                let t = unit_vector(tangent);
+                // Flip the bitangent where the texture is mirrored.
+                let b = if dot(&cross(n, t), &dpdv) < 0.0 { -cross(n, t) } else { cross(n, t) };
+                let c = image.value(u, v, p) * 2.0 - Vec3::ones();
+                unit_vector(t * c.x + b * c.y + n * c.z)
+            }
+            NormalMap::Bump { height, scale } => {
+                // Finite differences over one texel of the displaced surface.
+                let (du, dv) = (1.0 / height.width() as f64, 1.0 / height.height() as f64);
+                let h = |u: f64, v: f64| height.value(u, v, p).x * scale;
+                let h0 = h(u, v);
+                let dpdu = dpdu + n * ((h(u + du, v) - h0) / du);
+                let dpdv = dpdv + n * ((h(u, v + dv) - h0) / dv);
+                let bumped = unit_vector(cross(dpdu, dpdv));
+                if dot(&bumped, &n) < 0.0 { -bumped } else { bumped }
+            }
+        }
     }
 }
 
@@ -150,15 +218,15 @@ pub struct NoiseTexture {
 
 impl NoiseTexture {
     pub fn new(scale: f64) -> Self {
-        return Self {
+        Self {
             noise: Perlin::new(),
-            scale: scale,
-        };
+            scale,
+        }
     }
 
     fn turb(&self, p: &Vec3, depth: u32) -> f64 {
         let mut accum = 0.0;
-        let mut p = (*p).clone();
+        let mut p = *p ;
         let mut weight = 1.0;
         for _i in 0..depth {
             accum += self.noise.noise(&p) * weight;
@@ -170,7 +238,7 @@ impl NoiseTexture {
 }
 
 impl Texture for NoiseTexture {
-    fn value(&self, u:f64, v:f64, p:&Vec3) -> Vec3 {
+    fn value(&self, _u:f64, _v:f64, p:&Vec3) -> Vec3 {
         //return Vec3::ones() * self.noise.noise(p);
         Vec3::new(1.0, 1.0, 1.0) * 0.5 * (1.0 + (self.scale * p.z + 10.0 * self.turb(p, 7)).sin())
     }
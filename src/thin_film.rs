@@ -0,0 +1,151 @@
+use crate::{
+    hittable::HitRecord,
+    spectrum::{cie_xyz, xyz_to_film, Channels, LAMBDA_MAX, LAMBDA_MIN},
+    texture::{Surface, Texture},
+    utils::PI,
+    vec3::Color,
+};
+use std::ops::{Add, Div, Mul, Sub};
+
+// Wavelengths integrated over to get the RGB reflectance of a film.
+const RGB_STEPS: usize = 32;
+
+/// Transparent coating thin enough for its reflections to interfere, as in
+/// soap bubbles or oil slicks.
+#[derive(Clone)]
+pub struct ThinFilm {
+    /// Thickness as a fraction of `max_thickness`, from the red channel.
+    pub thickness: Surface,
+    /// Thickness in nanometres where `thickness` reads one.
+    pub max_thickness: f64,
+    pub ior: f64,
+}
+
+/// What the film lies on.
+#[derive(Clone, Copy, Debug)]
+pub enum Substrate {
+    /// Transparent medium of the given index of refraction.
+    Dielectric(f64),
+    /// Perfect conductor dimmed to the given reflectance per channel.
+    Conductor(Color),
+}
+
+impl ThinFilm {
+    /// Film `thickness` nanometres thick everywhere.
+    pub fn new(thickness: f64, ior: f64) -> Self {
+        Self { thickness: Surface::new_constant(1.0), max_thickness: thickness, ior }
+    }
+
+    /// Reflectance at `rec` of light arriving at `acos(cos_i)` from a medium
+    /// of index `eta_i`. Spectral hits get it at their wavelengths, others
+    /// the RGB color of the reflected spectrum.
+    pub fn reflectance(&self, rec: &HitRecord, cos_i: f64, eta_i: f64, substrate: Substrate) -> Color {
+        let d = self.thickness.value(rec.u, rec.v, &rec.p).x * self.max_thickness;
+        let airy = |lambda: f64, k: usize| self.airy(d, cos_i, eta_i, lambda, substrate, k);
+        if let Channels::Spectrum(w) = rec.channels {
+            return Color::from([airy(w.lambda[0], 0), airy(w.lambda[1], 1), airy(w.lambda[2], 2)])
+        }
+        // A conductor's reflectance differs between channels, so each one
+        // is integrated separately, against the white of the same sum.
+        let h = (LAMBDA_MAX - LAMBDA_MIN) / RGB_STEPS as f64;
+        let lambdas = (0..RGB_STEPS).map(|i| LAMBDA_MIN + (i as f64 + 0.5) * h);
+        let white = xyz_to_film(lambdas.clone().fold(Color::zero(), |sum, lambda| sum + cie_xyz(lambda)));
+        let channel = |k: usize| {
+            let xyz = lambdas.clone().fold(Color::zero(), |sum, lambda| sum + cie_xyz(lambda) * airy(lambda, k));
+            (xyz_to_film(xyz)[k] / white[k]).clamp(0.0, 1.0)
+        };
+        Color::from([channel(0), channel(1), channel(2)])
+    }
+
+    // Airy summation of the multiple reflections inside a film of thickness
+    // `d`, averaged over both polarisations.
+    fn airy(&self, d: f64, cos_i: f64, eta_i: f64, lambda: f64, substrate: Substrate, k: usize) -> f64 {
+        let sin2_i = 1.0 - cos_i * cos_i;
+        // Snell's law with complex cosines covers total internal reflection.
+        let cos_at = |eta: f64| Complex::sqrt(1.0 - sin2_i * (eta_i / eta).powi(2));
+        let cos_f = cos_at(self.ior);
+        let (r12_s, r12_p) = amplitudes(Complex::real(eta_i), Complex::real(cos_i), Complex::real(self.ior), cos_f);
+        let (r23_s, r23_p) = match substrate {
+            Substrate::Dielectric(eta_t) => amplitudes(Complex::real(self.ior), cos_f, Complex::real(eta_t), cos_at(eta_t)),
+            Substrate::Conductor(albedo) => {
+                let r = Complex::real(-albedo[k].max(0.0).sqrt());
+                (r, r)
+            }
+        };
+        // Phase difference of one round trip through the film.
+        let phase = Complex::exp_i(cos_f * (4.0 * PI * self.ior * d / lambda));
+        let r = |r12: Complex, r23: Complex| (r12 + r23 * phase) / (Complex::real(1.0) + r12 * r23 * phase);
+        0.5 * (r(r12_s, r23_s).norm_sqr() + r(r12_p, r23_p).norm_sqr())
+    }
+}
+
+// Fresnel amplitude coefficients `(s, p)` from medium 1 into medium 2, signed
+// so that both agree at normal incidence.
+fn amplitudes(eta1: Complex, cos1: Complex, eta2: Complex, cos2: Complex) -> (Complex, Complex) {
+    let s = (eta1 * cos1 - eta2 * cos2) / (eta1 * cos1 + eta2 * cos2);
+    let p = (eta1 * cos2 - eta2 * cos1) / (eta1 * cos2 + eta2 * cos1);
+    (s, p)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn real(re: f64) -> Self {
+        Self { re, im: 0.0 }
+    }
+
+    /// Principal square root of a real number.
+    fn sqrt(x: f64) -> Self {
+        if x >= 0.0 { Self { re: x.sqrt(), im: 0.0 } } else { Self { re: 0.0, im: (-x).sqrt() } }
+    }
+
+    /// `e^(iz)`.
+    fn exp_i(z: Complex) -> Self {
+        let m = (-z.im).exp();
+        Self { re: m * z.re.cos(), im: m * z.re.sin() }
+    }
+
+    fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Self { re: self.re + o.re, im: self.im + o.im }
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, o: Self) -> Self {
+        Self { re: self.re - o.re, im: self.im - o.im }
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, o: Self) -> Self {
+        Self { re: self.re * o.re - self.im * o.im, im: self.re * o.im + self.im * o.re }
+    }
+}
+
+impl Mul<f64> for Complex {
+    type Output = Self;
+    fn mul(self, s: f64) -> Self {
+        Self { re: self.re * s, im: self.im * s }
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+    fn div(self, o: Self) -> Self {
+        let d = o.norm_sqr();
+        Self { re: (self.re * o.re + self.im * o.im) / d, im: (self.im * o.re - self.re * o.im) / d }
+    }
+}
@@ -1,37 +1,31 @@
 use crate::{
     hittable::{HitRecord, Hittable},
     material::Material,
+    path_sample,
     ray::Ray,
-    vec3::{dot, Point3, unit_vector, cross},
+    vec3::{dot, Point3, Vec3, unit_vector, cross},
     aabb::Aabb,
-    utils::{fmax, fmin}
+    utils::{INFINITY, fmax, fmin}
 };
-use std::f64::consts::PI;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Triangle {
     a0: Point3,
     a1: Point3,
     a2: Point3,
-    material: Material,
+    material: Arc<Material>,
 }
 
 impl Triangle {
-    pub fn new(a0: Point3, a1: Point3, a2: Point3, material: Material) -> Self {
+    pub fn new(a0: Point3, a1: Point3, a2: Point3, material: impl Into<Arc<Material>>) -> Self {
         Self {
             a0,
             a1,
             a2,
-            material
+            material: material.into()
         }
     }
-
-    fn get_sphere_uv(p: &Point3, u: &mut f64, v: &mut f64) {
-        let phi = p.z.atan2(p.x);
-        let theta = p.y.asin();
-        *u = 1.0 - (phi + PI) / (2.0 * PI);
-        *v = (theta + PI / 2.0) / PI;
-    }
 }
 
 impl Hittable for Triangle {
@@ -48,14 +42,21 @@ impl Hittable for Triangle {
         if res.y<0.0 || res.z<0.0 || res.y+res.z>1.0 || res.x<t_min || res.x>t_max {
             return false
         }
+        if !self.material.opaque(res.y, res.z, &r.at(res.x)) {
+            return false
+        }
 
         rec.t = res.x;
         rec.p = r.at(rec.t);
         let outward_normal = unit_vector(cross(e1, e2));
         rec.set_face_normal(r, &outward_normal);
-        Triangle::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
-        rec.material = self.material.clone();
-        return true;
+        // Barycentric coordinates, so that textures span every triangle.
+        rec.u = res.y;
+        rec.v = res.z;
+        rec.dpdu = e1;
+        rec.dpdv = e2;
+        rec.set_material(&self.material);
+        true
     }
 
     fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
@@ -65,8 +66,40 @@ impl Hittable for Triangle {
         let x_mn = fmin(fmin(self.a0.x, self.a1.x),self.a2.x);
         let y_mn = fmin(fmin(self.a0.y, self.a1.y),self.a2.y);
         let z_mn = fmin(fmin(self.a0.z, self.a1.z),self.a2.z);
-        output_box.modify(Point3::from([x_mn+0.0001, y_mn+0.0001, z_mn+0.0001]), Point3::from([x_mx, y_mx, z_mx]));
+        output_box.modify(Point3::from([x_mn-0.0001, y_mn-0.0001, z_mn-0.0001]), Point3::from([x_mx+0.0001, y_mx+0.0001, z_mx+0.0001]));
         true
 
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction, 0.0), 0.001, INFINITY, &mut rec) {
+            return 0.0
+        }
+        let area = self.area();
+        let distance_squared = rec.t.powi(2) * direction.length_squared();
+        let cosine = (dot(&direction, &rec.normal) / direction.length()).abs();
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.random_point().0 - origin
+    }
+
+    fn area(&self) -> f64 {
+        0.5 * cross(self.a1 - self.a0, self.a2 - self.a0).length()
+    }
+
+    fn random_point(&self) -> (Point3, Vec3) {
+        // Uniform point on the triangle by folding the unit square.
+        let mut b1 = path_sample!();
+        let mut b2 = path_sample!();
+        if b1 + b2 > 1.0 {
+            b1 = 1.0 - b1;
+            b2 = 1.0 - b2;
+        }
+        let e1 = self.a1 - self.a0;
+        let e2 = self.a2 - self.a0;
+        (self.a0 + b1 * e1 + b2 * e2, unit_vector(cross(e1, e2)))
+    }
 }
@@ -1,6 +1,6 @@
 use crate::{random_double, utils::PI};
 use std::fmt;
-use std::ops::{Add, Div, Mul, MulAssign, Neg, Sub, Index};
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, Index};
 use ply_rs::ply;
 use crate::utils::{fmax, fmin};
 
@@ -138,6 +138,12 @@ impl Mul<Vec3> for f64 {
     }
 }
 
+impl AddAssign<Vec3> for Vec3 {
+    fn add_assign(&mut self, rhs: Vec3) {
+        *self = *self + rhs
+    }
+}
+
 impl MulAssign<Vec3> for Vec3 {
     fn mul_assign(&mut self, rhs: Vec3) {
         *self = Self {
@@ -228,6 +234,25 @@ pub fn random_in_unit_disk() -> Vec3 {
     }
 }
 
+pub fn random_cosine_direction() -> Vec3 {
+    let r1 = random_double!();
+    let r2 = random_double!();
+    let phi = 2.0 * PI * r1;
+    let x = f64::cos(phi) * f64::sqrt(r2);
+    let y = f64::sin(phi) * f64::sqrt(r2);
+    Vec3::from([x, y, f64::sqrt(1.0 - r2)])
+}
+
+pub fn random_to_sphere(radius: f64, distance_squared: f64) -> Vec3 {
+    let r1 = random_double!();
+    let r2 = random_double!();
+    let z = 1.0 + r2 * (f64::sqrt(1.0 - radius.powi(2) / distance_squared) - 1.0);
+    let phi = 2.0 * PI * r1;
+    let x = f64::cos(phi) * f64::sqrt(1.0 - z.powi(2));
+    let y = f64::sin(phi) * f64::sqrt(1.0 - z.powi(2));
+    Vec3::from([x, y, z])
+}
+
 pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
     v - 2.0 * dot(&v, &n) * n
 }
@@ -0,0 +1,92 @@
+use image::{ImageBuffer, Rgba};
+use rayt::{
+    hittable::{HitRecord, Hittable, Shape},
+    hittable_list::HittableList,
+    material::Material,
+    ray::Ray,
+    texture::{ImageTexture, Surface, Texture},
+    utils::INFINITY,
+    vec3::{Color, Point3, Vec3},
+};
+use std::sync::Arc;
+
+// Opaque on the right half of the image, clear on the left.
+fn half_mask() -> Surface {
+    let data = ImageBuffer::from_fn(8, 8, |x, _| Rgba([200, 100, 50, if x < 4 { 0 } else { 255 }]));
+    Surface::ImageTexture(ImageTexture { data: Arc::new(data) })
+}
+
+fn first_hit(world: &impl Hittable, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+    let mut rec = HitRecord::new();
+    world.hit(&Ray::new(origin, direction, 0.0), 0.001, INFINITY, &mut rec).then_some(rec)
+}
+
+#[test]
+fn test_image_alpha() {
+    let Surface::ImageTexture(image) = half_mask() else { unreachable!() };
+    assert_eq!(0.0, image.alpha(0.2, 0.5));
+    assert_eq!(1.0, image.alpha(0.8, 0.5));
+    // The color channels are read as before.
+    let c = image.value(0.8, 0.5, &Point3::zero());
+    assert!((c - Color::new(200.0, 100.0, 50.0) / 255.0).length() < 1e-12);
+    assert_eq!(0.3, Surface::new_constant(0.3).opacity(0.5, 0.5, &Point3::zero()));
+}
+
+#[test]
+fn test_cutout_quad() {
+    let mut world = HittableList::default();
+    let leaf = Material::new_alpha_masked(Material::new_lambertian(Color::ones()), half_mask(), Some(0.5));
+    world.add(Shape::new_quad(Point3::zero(), Vec3::from([1.0, 0.0, 0.0]), Vec3::from([0.0, 1.0, 0.0]), leaf));
+    world.add(Shape::new_quad(Point3::from([0.0, 0.0, -1.0]), Vec3::from([1.0, 0.0, 0.0]), Vec3::from([0.0, 1.0, 0.0]), Material::new_lambertian(Color::ones())));
+
+    let down = Vec3::from([0.0, 0.0, -1.0]);
+    let solid = first_hit(&world, Point3::from([0.8, 0.5, 1.0]), down).unwrap();
+    assert!(solid.p.z.abs() < 1e-12);
+    // The hit keeps the material under the mask.
+    assert!(matches!(solid.material(), Material::Lambertian(_)));
+    let through = first_hit(&world, Point3::from([0.2, 0.5, 1.0]), down).unwrap();
+    assert!((through.p.z + 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_cutout_leaves_record() {
+    // Acceleration structures pass the record of a nearer hit on to the
+    // shapes they visit next, so a cut-out hit must leave it alone.
+    let clear = Material::new_alpha_masked(Material::new_lambertian(Color::ones()), Surface::new_constant(0.0), Some(0.5));
+    let shapes = [
+        Shape::new_sphere(Point3::zero(), 1.0, clear.clone()),
+        Shape::new_quad(Point3::from([-1.0, -1.0, 0.0]), Vec3::from([2.0, 0.0, 0.0]), Vec3::from([0.0, 2.0, 0.0]), clear.clone()),
+        Shape::new_triangle(Point3::from([-1.0, -1.0, 0.0]), Point3::from([2.0, -1.0, 0.0]), Point3::from([-1.0, 2.0, 0.0]), clear.clone()),
+        Shape::new_cylinder(1.0, 2.0, clear),
+    ];
+    let ray = Ray::new(Point3::from([0.0, 0.5, 5.0]), Vec3::from([0.0, 0.0, -1.0]), 0.0);
+    for shape in shapes.iter() {
+        let mut rec = HitRecord::new();
+        rec.t = 0.25;
+        assert!(!shape.hit(&ray, 0.001, INFINITY, &mut rec));
+        assert_eq!(0.25, rec.t);
+        assert_eq!(Point3::zero(), rec.p);
+    }
+}
+
+#[test]
+fn test_cutout_sphere_shows_inside() {
+    // A cut-out near side lets the ray on to the far side of the sphere.
+    let hollow = Material::new_alpha_masked(Material::new_lambertian(Color::ones()), half_mask(), Some(0.5));
+    let sphere = Shape::new_sphere(Point3::zero(), 1.0, hollow);
+    let hits: Vec<f64> = [Vec3::from([1.0, 0.0, 0.0]), Vec3::from([-1.0, 0.0, 0.0])]
+        .iter()
+        .map(|&d| first_hit(&sphere, -3.0 * d, d).unwrap().t)
+        .collect();
+    assert!(hits.iter().any(|&t| (t - 2.0).abs() < 1e-9) && hits.iter().any(|&t| (t - 4.0).abs() < 1e-9));
+}
+
+#[test]
+fn test_stochastic_alpha() {
+    // Without a threshold, a surface of opacity a stops a fraction a of rays.
+    let gauze = Material::new_alpha_masked(Material::new_lambertian(Color::ones()), Surface::new_constant(0.3), None);
+    let quad = Shape::new_quad(Point3::zero(), Vec3::from([1.0, 0.0, 0.0]), Vec3::from([0.0, 1.0, 0.0]), gauze);
+    let n = 20000;
+    let hits = (0..n).filter(|_| first_hit(&quad, Point3::from([0.5, 0.5, 1.0]), Vec3::from([0.0, 0.0, -1.0])).is_some()).count();
+    assert!((hits as f64 / n as f64 - 0.3).abs() < 0.02);
+}
@@ -0,0 +1,612 @@
+use rayt::{
+    bsdf::{sample_uniforms, Bsdf, BsdfFlags},
+    camera::Camera,
+    hittable::{HitRecord, Hittable, Shape},
+    hittable_list::HittableList,
+    material::{Coated, Conductor, Material, Principled},
+    microfacet::{fresnel_conductor, fresnel_dielectric},
+    path_tracer::path_color,
+    ray::Ray,
+    render::{Background, Integrator, RenderSettings, Renderer},
+    spectrum::{Channels, Wavelengths},
+    texture::{Surface, Texture},
+    thin_film::{Substrate, ThinFilm},
+    utils::PI,
+    vec3::{dot, unit_vector, Color, Point3, Vec3},
+};
+use std::sync::Arc;
+
+fn record(material: &Material, front_face: bool) -> HitRecord {
+    let mut rec = HitRecord::new();
+    rec.material = Some(Arc::new(material.clone()));
+    rec.normal = Vec3::from([0.0, 0.0, 1.0]);
+    rec.front_face = front_face;
+    rec
+}
+
+fn uniforms(i: usize, n: usize) -> [f64; 3] {
+    let x = (i as f64 + 0.5) / n as f64;
+    [x, (x * 7.31).fract(), (x * 13.17).fract()]
+}
+
+#[test]
+fn test_lambertian_sample_matches_eval_and_pdf() {
+    let m = Material::new_lambertian(Color::from([0.2, 0.4, 0.6]));
+    let rec = record(&m, true);
+    let wo = unit_vector(Vec3::from([0.3, -0.2, 1.0]));
+    for i in 0..100 {
+        let s = m.sample(&rec, wo, uniforms(i, 100)).unwrap();
+        assert_eq!(BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION, s.flags);
+        assert!(!s.flags.is_specular());
+        let cosine = dot(&s.wi, &rec.normal);
+        assert!(cosine > 0.0);
+        assert!((m.pdf(&rec, wo, s.wi) - s.pdf).abs() < 1e-9);
+        let expected = m.eval(&rec, wo, s.wi) * cosine / s.pdf;
+        assert!((expected - s.weight).length() < 1e-9);
+        // Reciprocity.
+        assert!((m.eval(&rec, wo, s.wi) - m.eval(&rec, s.wi, wo)).length() < 1e-12);
+    }
+    let below = Vec3::from([0.0, 0.6, -0.8]);
+    assert_eq!(0.0, m.pdf(&rec, wo, below));
+    assert!(m.eval(&rec, wo, below).length() == 0.0);
+}
+
+#[test]
+fn test_specular_lobes_are_delta() {
+    let wo = unit_vector(Vec3::from([0.5, 0.0, 1.0]));
+    for m in [Material::new_metal(Color::ones(), 0.0), Material::new_dielectric(1.5)].iter() {
+        let rec = record(m, true);
+        for i in 0..20 {
+            let s = m.sample(&rec, wo, uniforms(i, 20)).unwrap();
+            assert!(s.flags.is_specular());
+            assert_eq!(0.0, m.pdf(&rec, wo, s.wi));
+            assert!(m.eval(&rec, wo, s.wi).length() == 0.0);
+        }
+    }
+
+    // A perfect mirror.
+    let metal = Material::new_metal(Color::ones(), 0.0);
+    let s = metal.sample(&record(&metal, true), wo, [0.5; 3]).unwrap();
+    assert!((s.wi - Vec3::from([-wo.x, -wo.y, wo.z])).length() < 1e-12);
+}
+
+#[test]
+fn test_dielectric_picks_lobes_by_reflectance() {
+    let glass = Material::new_dielectric(1.5);
+    let rec = record(&glass, true);
+    let wo = unit_vector(Vec3::from([0.5, 0.0, 1.0]));
+    let reflected = glass.sample(&rec, wo, [0.0, 0.5, 0.5]).unwrap();
+    let refracted = glass.sample(&rec, wo, [0.999, 0.5, 0.5]).unwrap();
+    assert!(reflected.flags.contains(BsdfFlags::REFLECTION));
+    assert!(refracted.flags.is_transmission());
+    assert!(dot(&refracted.wi, &rec.normal) < 0.0);
+    assert!((reflected.pdf + refracted.pdf - 1.0).abs() < 1e-12);
+
+    // Total internal reflection from inside at a grazing angle.
+    let inside = record(&glass, false);
+    let grazing = unit_vector(Vec3::from([1.0, 0.0, 0.2]));
+    let s = glass.sample(&inside, grazing, [0.999, 0.5, 0.5]).unwrap();
+    assert!(s.flags.contains(BsdfFlags::REFLECTION));
+    assert_eq!(1.0, s.pdf);
+}
+
+#[test]
+fn test_glass_absorbs_along_the_path_inside() {
+    let glass = Material::new_colored_glass(1.5, Color::from([0.5, 0.8, 1.0]), 1.0);
+    let wo = unit_vector(Vec3::from([0.2, 0.0, 1.0]));
+    // Entering costs nothing.
+    let mut rec = record(&glass, true);
+    rec.distance = 3.0;
+    for u in [[0.0, 0.5, 0.5], [0.999, 0.5, 0.5]].iter() {
+        assert!((glass.sample(&rec, wo, *u).unwrap().weight - Color::ones()).length() < 1e-12);
+    }
+    // Leaving after two units inside, or reflecting back in, transmits the
+    // color squared.
+    let mut rec = record(&glass, false);
+    rec.distance = 2.0;
+    let expected = Color::from([0.25, 0.64, 1.0]);
+    for u in [[0.0, 0.5, 0.5], [0.999, 0.5, 0.5]].iter() {
+        assert!((glass.sample(&rec, wo, *u).unwrap().weight - expected).length() < 1e-12);
+    }
+    let clear = Material::new_dielectric(1.5);
+    assert!((clear.sample(&record(&clear, false), wo, [0.999, 0.5, 0.5]).unwrap().weight - Color::ones()).length() == 0.0);
+
+    let sigma = Color::from([0.1, 0.2, 0.3]);
+    let absorbing = Material::new_absorbing_dielectric(1.5, sigma);
+    let mut rec = record(&absorbing, false);
+    rec.distance = 5.0;
+    let weight = absorbing.sample(&rec, wo, [0.999, 0.5, 0.5]).unwrap().weight;
+    assert!((weight.z - (-1.5f64).exp()).abs() < 1e-12);
+}
+
+#[test]
+fn test_glass_absorbs_along_non_unit_rays() {
+    // A ray from the center of a unit sphere with a direction of length 4
+    // hits at t = 0.25, one unit away.
+    let sigma = Color::from([0.5, 1.0, 2.0]);
+    let glass = Material::new_absorbing_dielectric(1.5, sigma);
+    let sphere = Shape::new_sphere(Point3::zero(), 1.0, glass.clone());
+    let r = Ray::new(Point3::zero(), Vec3::from([0.0, 0.0, 4.0]), 0.0);
+    let mut rec = HitRecord::new();
+    assert!(sphere.hit(&r, 0.001, f64::INFINITY, &mut rec));
+    assert!(!rec.front_face);
+    assert!((rec.t - 0.25).abs() < 1e-12);
+    let weight = glass.sample(&rec, -unit_vector(r.direction), [0.999, 0.5, 0.5]).unwrap().weight;
+    let expected = Color::from([(-0.5f64).exp(), (-1.0f64).exp(), (-2.0f64).exp()]);
+    assert!((weight - expected).length() < 1e-9);
+}
+
+#[test]
+fn test_rough_conductor_sample_matches_eval_and_pdf() {
+    let m = Material::new_conductor(Conductor::Gold, 0.4);
+    let rec = record(&m, true);
+    let wo = unit_vector(Vec3::from([0.6, 0.1, 0.5]));
+    let mut n = 0;
+    for i in 0..200 {
+        if let Some(s) = m.sample(&rec, wo, uniforms(i, 200)) {
+            n += 1;
+            assert_eq!(BsdfFlags::GLOSSY | BsdfFlags::REFLECTION, s.flags);
+            let cosine = dot(&s.wi, &rec.normal);
+            assert!(cosine > 0.0);
+            let pdf = m.pdf(&rec, wo, s.wi);
+            assert!((pdf - s.pdf).abs() < 1e-6 * pdf);
+            let expected = m.eval(&rec, wo, s.wi) * cosine / s.pdf;
+            assert!((expected - s.weight).length() < 1e-6);
+            assert!((m.eval(&rec, wo, s.wi) - m.eval(&rec, s.wi, wo)).length() < 1e-9);
+        }
+    }
+    assert!(n > 150);
+}
+
+#[test]
+fn test_rough_conductor_conserves_energy() {
+    // A perfect reflector loses energy only to shadowing and masking, and
+    // its sampled weights must agree with a uniform estimate of the albedo.
+    let m = Material::new_rough_conductor(Color::ones(), Color::from([1e4, 1e4, 1e4]), 0.5);
+    let rec = record(&m, true);
+    let wo = unit_vector(Vec3::from([0.3, 0.0, 1.0]));
+    let n = 100_000;
+    let sampled = (0..n)
+        .filter_map(|_| m.sample(&rec, wo, sample_uniforms()))
+        .fold(0.0, |sum, s| sum + s.weight.x) / n as f64;
+    let uniform = (0..n)
+        .map(|_| {
+            let [_, z, u2] = sample_uniforms();
+            let r = (1.0 - z * z).sqrt();
+            let wi = Vec3::from([r * (2.0 * PI * u2).cos(), r * (2.0 * PI * u2).sin(), z]);
+            m.eval(&rec, wo, wi).x * z * 2.0 * PI
+        })
+        .sum::<f64>() / n as f64;
+    assert!(sampled <= 1.0 && sampled > 0.9);
+    assert!((sampled - uniform).abs() < 0.02);
+}
+
+#[test]
+fn test_conductor_presets() {
+    for metal in [Conductor::Gold, Conductor::Copper, Conductor::Silver, Conductor::Aluminium, Conductor::Brass].iter() {
+        let (eta, k) = metal.ior();
+        let f0 = fresnel_conductor(1.0, eta, k);
+        let expected = ((eta + -1.0) * (eta + -1.0) + k * k) / ((eta + 1.0) * (eta + 1.0) + k * k);
+        assert!((f0 - expected).length() < 1e-9);
+        // Reflectance rises to one at grazing incidence.
+        assert!((fresnel_conductor(0.0, eta, k) - Color::ones()).length() < 1e-9);
+    }
+    let (eta, k) = Conductor::Gold.ior();
+    let gold = fresnel_conductor(1.0, eta, k);
+    assert!(gold.x > gold.y && gold.y > gold.z);
+    let (eta, k) = Conductor::Silver.ior();
+    assert!(fresnel_conductor(1.0, eta, k).z > 0.9);
+}
+
+#[test]
+fn test_rough_dielectric_sample_matches_eval_and_pdf() {
+    let m = Material::new_rough_dielectric(1.5, 0.3);
+    let wo = unit_vector(Vec3::from([0.4, -0.3, 1.0]));
+    for front_face in [true, false].iter() {
+        let rec = record(&m, *front_face);
+        let (mut reflected, mut transmitted) = (0, 0);
+        for _ in 0..2000 {
+            if let Some(s) = m.sample(&rec, wo, sample_uniforms()) {
+                let cosine = dot(&s.wi, &rec.normal);
+                if s.flags.is_transmission() {
+                    transmitted += 1;
+                    assert!(cosine < 0.0);
+                } else {
+                    reflected += 1;
+                    assert!(cosine > 0.0);
+                }
+                let pdf = m.pdf(&rec, wo, s.wi);
+                assert!((pdf - s.pdf).abs() < 1e-6 * pdf);
+                let expected = m.eval(&rec, wo, s.wi) * cosine.abs() / s.pdf;
+                assert!((expected - s.weight).length() < 1e-6);
+            }
+        }
+        assert!(reflected > 0 && transmitted > 0);
+    }
+}
+
+#[test]
+fn test_rough_dielectric_white_furnace() {
+    // Reflection plus transmission keep all energy but what shadowing and
+    // masking remove, and the sampled weights agree with a quadrature of eval.
+    let n = 100_000;
+    for roughness in [0.3, 0.6].iter() {
+        let m = Material::new_rough_dielectric(1.5, *roughness);
+        let rec = record(&m, true);
+        let wo = unit_vector(Vec3::from([0.5, 0.0, 1.0]));
+        let sampled = (0..n)
+            .filter_map(|_| m.sample(&rec, wo, sample_uniforms()))
+            .fold(0.0, |sum, s| sum + s.weight.x) / n as f64;
+        // Midpoint rule over the sphere, uniform in z and azimuth.
+        let steps = 600;
+        let mut quadrature = 0.0;
+        for i in 0..steps {
+            let z = 1.0 - 2.0 * (i as f64 + 0.5) / steps as f64;
+            let r = (1.0 - z * z).sqrt();
+            for j in 0..steps {
+                let phi = 2.0 * PI * (j as f64 + 0.5) / steps as f64;
+                let wi = Vec3::from([r * phi.cos(), r * phi.sin(), z]);
+                quadrature += m.eval(&rec, wo, wi).x * z.abs();
+            }
+        }
+        quadrature *= 4.0 * PI / (steps * steps) as f64;
+        assert!(sampled <= 1.0 && sampled > 0.9);
+        assert!((sampled - quadrature).abs() < 0.01);
+    }
+    assert!(fresnel_dielectric(1.0, 1.5) > 0.039 && fresnel_dielectric(1.0, 1.5) < 0.041);
+    assert_eq!(1.0, fresnel_dielectric(0.1, 1.0 / 1.5));
+
+    // A frosted glass ball vanishes against a uniform white background.
+    let mut world = HittableList::default();
+    world.add(Shape::new_sphere(Point3::zero(), 0.5, Material::new_rough_dielectric(1.5, 0.2)));
+    let cam = Camera::new(
+        Point3::from([0.0, 0.0, 2.0]),
+        Point3::zero(),
+        Vec3::from([0.0, 1.0, 0.0]),
+        40.0,
+        1.0,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+    );
+    let mut settings = RenderSettings::new(8, 1.0, 64, 50);
+    settings.background = Background::Solid(Color::ones());
+    settings.integrator = Integrator::PathTracer;
+    let fb = Renderer::new(settings).render(&cam, &world);
+    let mean = fb.data.iter().fold(0.0, |sum, c| sum + c.x) / fb.data.len() as f64;
+    assert!((mean - 1.0).abs() < 0.02);
+}
+
+fn quadrature(m: &Material, rec: &HitRecord, wo: Vec3) -> Color {
+    // Midpoint rule over the sphere, uniform in z and azimuth.
+    let steps = 600;
+    let mut sum = Color::zero();
+    for i in 0..steps {
+        let z = 1.0 - 2.0 * (i as f64 + 0.5) / steps as f64;
+        let r = (1.0 - z * z).sqrt();
+        for j in 0..steps {
+            let phi = 2.0 * PI * (j as f64 + 0.5) / steps as f64;
+            let wi = Vec3::from([r * phi.cos(), r * phi.sin(), z]);
+            sum += m.eval(rec, wo, wi) * z.abs();
+        }
+    }
+    sum * (4.0 * PI / (steps * steps) as f64)
+}
+
+#[test]
+fn test_principled_sample_matches_eval_and_pdf() {
+    let mut p = Principled::new(Surface::new_solid_color(Color::from([0.8, 0.5, 0.3])));
+    p.metallic = Surface::new_constant(0.2);
+    p.roughness = Surface::new_constant(0.4);
+    p.specular_tint = Surface::new_constant(0.5);
+    p.sheen = Surface::new_constant(1.0);
+    p.clearcoat = Surface::new_constant(1.0);
+    p.transmission = Surface::new_constant(0.5);
+    let m = Material::new_principled(p);
+    let wo = unit_vector(Vec3::from([0.4, -0.3, 1.0]));
+    for front_face in [true, false].iter() {
+        let rec = record(&m, *front_face);
+        let (mut reflected, mut transmitted) = (0, 0);
+        for _ in 0..2000 {
+            if let Some(s) = m.sample(&rec, wo, sample_uniforms()) {
+                assert!(!s.flags.is_specular());
+                let cosine = dot(&s.wi, &rec.normal);
+                if s.flags.is_transmission() {
+                    transmitted += 1;
+                    assert!(cosine < 0.0);
+                } else {
+                    reflected += 1;
+                    assert!(cosine > 0.0);
+                    assert!((m.eval(&rec, wo, s.wi) - m.eval(&rec, s.wi, wo)).length() < 1e-9);
+                }
+                let pdf = m.pdf(&rec, wo, s.wi);
+                assert!((pdf - s.pdf).abs() < 1e-6 * pdf);
+                let expected = m.eval(&rec, wo, s.wi) * cosine.abs() / s.pdf;
+                assert!((expected - s.weight).length() < 1e-6);
+            }
+        }
+        assert!(reflected > 0 && transmitted > 0);
+    }
+}
+
+#[test]
+fn test_principled_albedo() {
+    // Sampled weights agree with a quadrature of eval across the parameter
+    // space. The lobes are added rather than layered, so a white surface may
+    // return a few percent more than it receives, as in Burley's model.
+    let n = 100_000;
+    let wo = unit_vector(Vec3::from([0.5, 0.0, 1.0]));
+    let setups: [fn(&mut Principled); 4] = [
+        |_| {},
+        |p| p.metallic = Surface::new_constant(1.0),
+        |p| p.transmission = Surface::new_constant(1.0),
+        |p| {
+            p.clearcoat = Surface::new_constant(1.0);
+            p.clearcoat_gloss = Surface::new_constant(0.5);
+            p.sheen = Surface::new_constant(1.0);
+        },
+    ];
+    for setup in setups.iter() {
+        let mut p = Principled::new(Surface::new_constant(1.0));
+        setup(&mut p);
+        let m = Material::new_principled(p);
+        let rec = record(&m, true);
+        let sampled = (0..n)
+            .filter_map(|_| m.sample(&rec, wo, sample_uniforms()))
+            .fold(0.0, |sum, s| sum + s.weight.x) / n as f64;
+        let expected = quadrature(&m, &rec, wo).x;
+        assert!((sampled - expected).abs() < 0.02);
+        assert!(expected > 0.8 && expected < 1.1);
+    }
+}
+
+#[test]
+fn test_principled_parameters_follow_textures() {
+    // Metal where the test texture is bright, half metal elsewhere.
+    let mut p = Principled::new(Surface::new_solid_color(Color::from([0.9, 0.1, 0.1])));
+    p.metallic = Surface::new_test_texture(Color::ones());
+    p.roughness = Surface::new_constant(0.3);
+    let m = Material::new_principled(p);
+    let mut metal = record(&m, true);
+    let mut mixed = record(&m, true);
+    metal.p = Point3::from([-0.1, 0.1, 0.1]);
+    mixed.p = Point3::from([0.1, 0.1, 0.1]);
+    let wo = unit_vector(Vec3::from([0.3, 0.0, 1.0]));
+    let wi = Vec3::from([-wo.x, -wo.y, wo.z]);
+    let (a, b) = (m.eval(&metal, wo, wi), m.eval(&mixed, wo, wi));
+    assert!(a.x > b.x);
+    assert!(a.y / a.x < b.y / b.x);
+
+    // Specular scales the dielectric reflectance at normal incidence.
+    let rec = record(&m, true);
+    let up = Vec3::from([0.0, 0.0, 1.0]);
+    let reflectance = |specular: f64| {
+        let mut p = Principled::new(Surface::new_constant(0.0));
+        p.specular = Surface::new_constant(specular);
+        Material::new_principled(p).eval(&rec, up, up).x
+    };
+    assert!((reflectance(1.0) / reflectance(0.5) - 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_thin_film_reflectance() {
+    let mut rec = record(&Material::new_lambertian(Color::zero()), true);
+    // Without thickness the film drops out, whatever its index.
+    let bare = fresnel_dielectric(0.7, 1.5);
+    let r = ThinFilm::new(0.0, 1.33).reflectance(&rec, 0.7, 1.0, Substrate::Dielectric(1.5));
+    assert!((r - Color::ones() * bare).length() < 1e-3);
+    let r = ThinFilm::new(300.0, 1.0).reflectance(&rec, 0.7, 1.0, Substrate::Conductor(Color::from([0.9, 0.5, 0.2])));
+    assert!((r - Color::from([0.9, 0.5, 0.2])).length() < 1e-3);
+
+    // A quarter-wave coating of index sqrt(1.5) cancels the reflection off
+    // glass at its design wavelength; a half-wave one leaves it as it was.
+    rec.channels = Channels::Spectrum(Wavelengths { lambda: [550.0; 3] });
+    let eta = 1.5f64.sqrt();
+    let quarter = ThinFilm::new(550.0 / (4.0 * eta), eta).reflectance(&rec, 1.0, 1.0, Substrate::Dielectric(1.5));
+    assert!(quarter.x < 1e-9);
+    let half = ThinFilm::new(550.0 / (2.0 * eta), eta).reflectance(&rec, 1.0, 1.0, Substrate::Dielectric(1.5));
+    assert!((half.x - 0.04).abs() < 1e-9);
+    // Light inside glass gets the same, as the film is lossless.
+    let inside = ThinFilm::new(550.0 / (4.0 * eta), eta).reflectance(&rec, 1.0, 1.5, Substrate::Dielectric(1.0));
+    assert!(inside.x < 1e-9);
+}
+
+#[test]
+fn test_soap_film_is_colored_and_conserves_energy() {
+    let m = Material::new_thin_film_dielectric(1.0, ThinFilm::new(400.0, 1.33));
+    let rec = record(&m, true);
+    let wo = unit_vector(Vec3::from([0.3, 0.0, 1.0]));
+    let n = 20_000;
+    let (mut reflected, mut total) = (Color::zero(), Color::zero());
+    for i in 0..n {
+        let s = m.sample(&rec, wo, uniforms(i, n)).unwrap();
+        assert!(s.flags.is_specular());
+        if s.flags.contains(BsdfFlags::REFLECTION) {
+            reflected += s.weight;
+        }
+        total += s.weight;
+    }
+    let (reflected, total) = (reflected / n as f64, total / n as f64);
+    assert!((total - Color::ones()).length() < 1e-2);
+    // Interference tints the reflection.
+    let (lo, hi) = (reflected.x.min(reflected.y).min(reflected.z), reflected.x.max(reflected.y).max(reflected.z));
+    assert!(hi > 0.0 && hi - lo > 0.2 * hi);
+}
+
+#[test]
+fn test_coated_white_furnace() {
+    // A clear coat returns everything a white base reflects, however often
+    // it bounces under the coating first.
+    let n = 100_000;
+    let wo = unit_vector(Vec3::from([0.5, 0.0, 1.0]));
+    for roughness in [0.0, 0.3] {
+        let mut coated = Coated::new(Material::new_lambertian(Color::ones()));
+        coated.roughness = roughness;
+        let m = Material::new_coated(coated);
+        let rec = record(&m, true);
+        let sampled = (0..n)
+            .filter_map(|_| m.sample(&rec, wo, sample_uniforms()))
+            .fold(Color::zero(), |sum, s| sum + s.weight)
+            / n as f64;
+        assert!((sampled - Color::ones()).length() < 0.03);
+        assert!((quadrature(&m, &rec, wo) - Color::ones()).length() < 0.03);
+    }
+}
+
+#[test]
+fn test_coated_sample_matches_pdf() {
+    let mut coated = Coated::new(Material::new_rough_conductor(Color::ones(), Color::ones() * 3.0, 0.4));
+    coated.roughness = 0.2;
+    let m = Material::new_coated(coated);
+    let rec = record(&m, true);
+    let wo = unit_vector(Vec3::from([0.3, -0.2, 1.0]));
+    for i in 0..200 {
+        if let Some(s) = m.sample(&rec, wo, uniforms(i, 200)) {
+            assert!(!s.flags.is_specular());
+            assert!(dot(&s.wi, &rec.normal) > 0.0);
+            assert!((m.pdf(&rec, wo, s.wi) - s.pdf).abs() < 1e-9 * s.pdf.max(1.0));
+        }
+    }
+}
+
+#[test]
+fn test_coating_absorbs() {
+    let n = 50_000;
+    let wo = unit_vector(Vec3::from([0.5, 0.0, 1.0]));
+    let mut coated = Coated::new(Material::new_lambertian(Color::ones()));
+    coated.absorption = Color::from([0.0, 2.0, 2.0]);
+    coated.thickness = 0.5;
+    let m = Material::new_coated(coated);
+    let rec = record(&m, true);
+    let sampled = (0..n)
+        .filter_map(|_| m.sample(&rec, wo, sample_uniforms()))
+        .fold(Color::zero(), |sum, s| sum + s.weight)
+        / n as f64;
+    assert!((sampled.x - 1.0).abs() < 0.03);
+    assert!((sampled.y - sampled.z).abs() < 0.02);
+    assert!(sampled.y < 0.3);
+}
+
+#[test]
+fn test_coated_mirror_is_delta() {
+    let m = Material::new_coated(Coated::new(Material::new_metal(Color::ones(), 0.0)));
+    let mut rec = record(&m, true);
+    // Metal is checkered; keep to a square of full albedo.
+    rec.p = Point3::from([0.1, 0.1, -0.1]);
+    let wo = unit_vector(Vec3::from([0.5, 0.0, 1.0]));
+    let wi = Vec3::from([-wo.x, -wo.y, wo.z]);
+    assert_eq!(Color::zero(), m.eval(&rec, wo, wi));
+    assert_eq!(0.0, m.pdf(&rec, wo, wi));
+    let n = 10_000;
+    let mut total = Color::zero();
+    let mut mirrored = 0;
+    for i in 0..n {
+        let s = m.sample(&rec, wo, uniforms(i, n)).unwrap();
+        assert!(s.flags.is_specular());
+        if (s.wi - wi).length() < 1e-2 {
+            mirrored += 1;
+        }
+        total += s.weight;
+    }
+    // A smooth coat over a mirror reflects along the mirror direction
+    // whichever layer the light came off, up to the tails of the highlight.
+    assert!(mirrored as f64 > 0.95 * n as f64);
+    assert!((total / n as f64 - Color::ones()).length() < 0.03);
+}
+
+#[test]
+fn test_mix_sample_matches_eval_and_pdf() {
+    let a = Material::new_lambertian(Color::from([0.8, 0.4, 0.2]));
+    let b = Material::new_conductor(Conductor::Copper, 0.3);
+    let m = Material::new_mix(a.clone(), b.clone(), 0.3);
+    let rec = record(&m, true);
+    let wo = unit_vector(Vec3::from([0.3, -0.2, 1.0]));
+    let wi = unit_vector(Vec3::from([-0.1, 0.4, 1.0]));
+    assert!((m.eval(&rec, wo, wi) - (a.eval(&rec, wo, wi) * 0.7 + b.eval(&rec, wo, wi) * 0.3)).length() < 1e-12);
+    for i in 0..200 {
+        if let Some(s) = m.sample(&rec, wo, uniforms(i, 200)) {
+            let cosine = dot(&s.wi, &rec.normal);
+            assert!(cosine > 0.0);
+            assert!((m.pdf(&rec, wo, s.wi) - s.pdf).abs() < 1e-9 * s.pdf.max(1.0));
+            let expected = m.eval(&rec, wo, s.wi) * cosine / s.pdf;
+            assert!((expected - s.weight).length() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn test_mix_with_specular() {
+    // Glass reflects or transmits everything, as does a white diffuser in
+    // the furnace, and the glass takes its share of the samples as deltas.
+    let m = Material::new_mix(Material::new_lambertian(Color::ones()), Material::new_dielectric(1.5), 0.25);
+    let rec = record(&m, true);
+    let wo = unit_vector(Vec3::from([0.5, 0.0, 1.0]));
+    let n = 10_000;
+    let (mut total, mut specular) = (Color::zero(), 0);
+    for i in 0..n {
+        let s = m.sample(&rec, wo, uniforms(i, n)).unwrap();
+        if s.flags.is_specular() {
+            specular += 1;
+        }
+        total += s.weight;
+    }
+    assert!((specular as f64 / n as f64 - 0.25).abs() < 0.01);
+    assert!((total / n as f64 - Color::ones()).length() < 1e-9);
+}
+
+#[test]
+fn test_mix_follows_texture() {
+    // Rust where the mask is white, bare metal where it is black.
+    let rust = Material::new_lambertian(Color::from([0.5, 0.2, 0.1]));
+    let metal = Material::new_rough_conductor(Color::ones(), Color::ones() * 3.0, 0.3);
+    let mask = Surface::new_image_texture("assets/dimples_height.png");
+    let m = Material::new_mix_tex(metal.clone(), rust.clone(), mask.clone());
+    let mut rec = record(&m, true);
+    let wo = unit_vector(Vec3::from([0.3, -0.2, 1.0]));
+    let wi = unit_vector(Vec3::from([-0.1, 0.4, 1.0]));
+    for (u, v) in [(0.1, 0.2), (0.45, 0.7), (0.8, 0.5)] {
+        rec.u = u;
+        rec.v = v;
+        let w = mask.value(u, v, &rec.p).x;
+        let expected = metal.eval(&rec, wo, wi) * (1.0 - w) + rust.eval(&rec, wo, wi) * w;
+        assert!((m.eval(&rec, wo, wi) - expected).length() < 1e-12);
+    }
+}
+
+// Mean radiance over `n` paths hitting the middle of a unit ball of
+// `material` under a uniform white sky.
+fn furnace_ball(material: Material, n: usize) -> Color {
+    let mut world = HittableList::default();
+    world.add(Shape::new_sphere(Point3::zero(), 1.0, material));
+    let background = Background::Solid(Color::ones());
+    let lights = HittableList::default();
+    let ray = Ray::new(Point3::from([0.0, 0.0, 5.0]), Vec3::from([0.0, 0.0, -1.0]), 0.0);
+    (0..n).fold(Color::zero(), |sum, _| sum + path_color(&ray, &world, &lights, &background, 10_000)) / n as f64
+}
+
+#[test]
+fn test_subsurface_white_furnace() {
+    // Without absorption every walk leaves the ball again, however long.
+    let white = furnace_ball(Material::new_subsurface(Color::ones(), Color::ones() * 0.05, 1.4), 200);
+    for k in 0..3 {
+        assert!((white[k] - 1.0).abs() < 1e-6);
+    }
+    // Channels that scatter at different rates only come out white on
+    // average; the mean of 8000 paths has a spread of about 0.013.
+    let tinted = furnace_ball(Material::new_subsurface(Color::ones(), Color::from([0.1, 0.05, 0.025]), 1.4), 8000);
+    for k in 0..3 {
+        assert!((tinted[k] - 1.0).abs() < 0.05);
+    }
+}
+
+#[test]
+fn test_subsurface_matches_albedo() {
+    // A ball much wider than the mean free path reflects close to its
+    // albedo, a little less as the light arrives head-on rather than diffuse.
+    let albedo = Color::from([0.8, 0.5, 0.2]);
+    let c = furnace_ball(Material::new_subsurface(albedo, Color::ones() * 0.02, 1.0), 2000);
+    for k in 0..3 {
+        assert!(c[k] < albedo[k] + 0.02 && c[k] > albedo[k] - 0.08);
+    }
+}
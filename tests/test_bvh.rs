@@ -0,0 +1,100 @@
+use rayt::{
+    aabb::Aabb,
+    bvh::BvhNode,
+    kdtree::KdTree,
+    octree::Octree,
+    linear_bvh::LinearBvh,
+    hittable::{HitRecord, Hittable, Shape},
+    hittable_list::HittableList,
+    material::Material,
+    random_double,
+    ray::Ray,
+    utils::INFINITY,
+    vec3::{random_unit_vector, Color, Point3, Vec3},
+};
+
+fn random_world() -> HittableList {
+    let mut world = HittableList::default();
+    let mat = Material::new_lambertian(Color::ones());
+    for _ in 0..200 {
+        let c = Vec3::random(Some([-10.0, 10.0]));
+        world.add(Shape::new_sphere(c, random_double!(0.1, 1.0), mat.clone()));
+        let a = Vec3::random(Some([-10.0, 10.0]));
+        world.add(Shape::new_triangle(a, a + Vec3::random(None), a + Vec3::random(None), mat.clone()));
+    }
+    world.add(Shape::new_triangle(
+        Point3::from([1000.0, -11.0, 0.0]),
+        Point3::from([0.0, -11.0, -1000.0]),
+        Point3::from([0.0, -11.0, 1000.0]),
+        mat,
+    ));
+    world
+}
+
+fn closest<H: Hittable>(world: &H, r: &Ray) -> Option<f64> {
+    let mut rec = HitRecord::new();
+    if world.hit(r, 0.001, INFINITY, &mut rec) {
+        Some(rec.t)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_bvh_matches_brute_force() {
+    let world = random_world();
+    let bvh = BvhNode::new_sah(&world.objects);
+    let mut median_objects = world.objects.clone();
+    let end = median_objects.len();
+    let median = BvhNode::new(&mut median_objects, 0, end);
+    let linear = LinearBvh::new(world.objects.clone());
+    assert_eq!(world.objects.len(), linear.len());
+    let kdtree = KdTree::new(world.objects.clone());
+    let shallow = KdTree::with_limits(world.objects.clone(), 4, 8);
+    let octree = Octree::new(HittableList { objects: world.objects.clone() });
+    for _ in 0..2000 {
+        let r = Ray::new(Vec3::random(Some([-15.0, 15.0])), random_unit_vector(), 0.0);
+        let expected = closest(&world, &r);
+        assert_eq!(expected, closest(&bvh, &r));
+        assert_eq!(expected, closest(&median, &r));
+        assert_eq!(expected, closest(&linear, &r));
+        assert_eq!(expected, closest(&kdtree, &r));
+        assert_eq!(expected, closest(&shallow, &r));
+        assert_eq!(expected, closest(&octree, &r));
+    }
+}
+
+#[test]
+fn test_empty_scenes() {
+    // Every accelerator builds over no objects, and nothing hits the result.
+    let r = Ray::new(Point3::zero(), Vec3::from([0.0, 0.0, -1.0]), 0.0);
+    let mut bbox = Aabb::default();
+    let sah = BvhNode::new_sah(&[]);
+    let median = BvhNode::new(&mut Vec::new(), 0, 0);
+    assert!(!sah.bounding_box(0.0, 0.0, &mut bbox) && !median.bounding_box(0.0, 0.0, &mut bbox));
+    assert_eq!(None, closest(&sah, &r));
+    assert_eq!(None, closest(&median, &r));
+    assert_eq!(None, closest(&LinearBvh::new(Vec::new()), &r));
+    assert_eq!(None, closest(&KdTree::new(Vec::new()), &r));
+    assert_eq!(None, closest(&Octree::new(HittableList::default()), &r));
+}
+
+#[test]
+fn test_octree_sphere_grid() {
+    // Equal spheres on a plane, as in the logo scenes, with a ground sphere
+    // that straddles every cell.
+    let mut world = HittableList::default();
+    let mat = Material::new_lambertian(Color::ones());
+    world.add(Shape::new_sphere(Point3::from([0.0, -1000.0, 0.0]), 1000.0, mat.clone()));
+    for a in 0..40 {
+        for b in 0..20 {
+            world.add(Shape::new_sphere(Point3::new(a as f64 / 5.0 - 4.0, b as f64 / 5.0, 0.0), 0.18, mat.clone()));
+        }
+    }
+    let octree = Octree::new(HittableList { objects: world.objects.clone() });
+    assert_eq!(world.objects.len(), octree.len());
+    for _ in 0..2000 {
+        let r = Ray::new(Vec3::random(Some([-8.0, 8.0])) + Vec3::from([0.0, 8.0, 0.0]), random_unit_vector(), 0.0);
+        assert_eq!(closest(&world, &r), closest(&octree, &r));
+    }
+}
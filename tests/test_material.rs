@@ -0,0 +1,50 @@
+use rayt::{
+    hittable::{HitRecord, Hittable, Shape},
+    hittable_list::HittableList,
+    linear_bvh::LinearBvh,
+    material::{Coated, Material},
+    ray::Ray,
+    texture::NormalMap,
+    utils::INFINITY,
+    vec3::{Color, Point3, Vec3},
+};
+use std::sync::Arc;
+
+#[test]
+fn test_hits_share_materials() {
+    // A textured mesh keeps one material, and one image, for all its faces.
+    let mut world = HittableList::default();
+    let textured = Arc::new(Material::new_normal_mapped(Material::new_image_tex("assets/leaf.png"), "assets/tiles_normal.png"));
+    let Material::NormalMapped(mapped) = textured.as_ref() else { unreachable!() };
+    for i in 0..100 {
+        let x = i as f64;
+        world.add(Shape::new_triangle(Point3::from([x, 0.0, 0.0]), Point3::from([x + 1.0, 0.0, 0.0]), Point3::from([x, 1.0, 0.0]), textured.clone()));
+    }
+    // One reference per face, and the one held here.
+    assert_eq!(101, Arc::strong_count(&textured));
+    let bvh = LinearBvh::new(world.objects);
+    for i in 0..100 {
+        let mut rec = HitRecord::new();
+        let r = Ray::new(Point3::from([i as f64 + 0.2, 0.2, 1.0]), Vec3::from([0.0, 0.0, -1.0]), 0.0);
+        assert!(bvh.hit(&r, 0.001, INFINITY, &mut rec));
+        // The hit takes the base under the normal map by reference.
+        assert!(Arc::ptr_eq(&mapped.base, rec.material.as_ref().unwrap()));
+    }
+
+    let copy = textured.as_ref().clone();
+    let (Material::NormalMapped(a), Material::NormalMapped(b)) = (textured.as_ref(), &copy) else { unreachable!() };
+    let (NormalMap::Normal(a), NormalMap::Normal(b)) = (&a.map, &b.map) else { unreachable!() };
+    assert!(Arc::ptr_eq(&a.data, &b.data));
+
+    // Mixes share the materials they blend.
+    let mix = Material::new_mix(textured.clone(), Material::new_lambertian(Color::ones()), 0.5);
+    let copy = mix.clone();
+    let (Material::Mix(a), Material::Mix(b)) = (&mix, &copy) else { unreachable!() };
+    assert!(Arc::ptr_eq(&a.a, &textured) && Arc::ptr_eq(&a.a, &b.a) && Arc::ptr_eq(&a.b, &b.b));
+
+    // So do coatings and what they cover.
+    let coated = Material::new_coated(Coated::new(textured.clone()));
+    let copy = coated.clone();
+    let (Material::Coated(a), Material::Coated(b)) = (&coated, &copy) else { unreachable!() };
+    assert!(Arc::ptr_eq(&a.base, &textured) && Arc::ptr_eq(&a.base, &b.base));
+}
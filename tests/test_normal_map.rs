@@ -0,0 +1,108 @@
+use image::{ImageBuffer, Rgba};
+use rayt::{
+    hittable::{HitRecord, Hittable, Shape},
+    material::{Material, NormalMapped},
+    ray::Ray,
+    texture::{ImageTexture, NormalMap},
+    utils::INFINITY,
+    vec3::{dot, unit_vector, Color, Point3, Vec3},
+};
+use std::sync::Arc;
+
+fn hit(shape: &Shape, origin: Point3, target: Point3) -> HitRecord {
+    let mut rec = HitRecord::new();
+    assert!(shape.hit(&Ray::new(origin, target - origin, 0.0), 0.001, INFINITY, &mut rec));
+    rec
+}
+
+fn image(width: u32, pixel: impl Fn(u32, u32) -> [u8; 3]) -> ImageTexture {
+    let opaque = |x, y| {
+        let [r, g, b] = pixel(x, y);
+        Rgba([r, g, b, 255])
+    };
+    ImageTexture { data: Arc::new(ImageBuffer::from_fn(width, width, opaque)) }
+}
+
+fn unit_quad(material: Material) -> Shape {
+    Shape::new_quad(Point3::zero(), Vec3::from([1.0, 0.0, 0.0]), Vec3::from([0.0, 1.0, 0.0]), material)
+}
+
+#[test]
+fn test_tangents_follow_uv() {
+    // Stepping along `dpdu` moves `u` alone, and along `dpdv` moves `v` alone.
+    let white = Material::new_lambertian(Color::ones());
+    let cases = [
+        (Shape::new_sphere(Point3::from([0.0, 1.0, 0.0]), 2.0, white.clone()), Point3::from([3.0, 4.0, 5.0]), Point3::from([0.0, 1.0, 0.0])),
+        (
+            Shape::new_triangle(Point3::zero(), Point3::from([2.0, 0.0, 0.0]), Point3::from([0.0, 1.0, 1.0]), white.clone()),
+            Point3::from([1.0, 1.0, -2.0]),
+            Point3::from([0.5, 0.2, 0.2]),
+        ),
+        (
+            Shape::new_mesh(
+                Point3::zero(),
+                Point3::from([2.0, 0.0, 0.0]),
+                Point3::from([0.0, 1.0, 1.0]),
+                Vec3::from([0.0, -1.0, 1.0]),
+                Vec3::from([0.0, -1.0, 1.0]),
+                Vec3::from([0.0, -1.0, 1.0]),
+                white.clone(),
+            ),
+            Point3::from([1.0, -2.0, 2.0]),
+            Point3::from([0.5, 0.2, 0.2]),
+        ),
+        (Shape::new_quad(Point3::zero(), Vec3::from([2.0, 0.0, 1.0]), Vec3::from([0.0, 3.0, 0.0]), white.clone()), Point3::from([1.0, 1.0, 5.0]), Point3::from([1.0, 1.5, 0.5])),
+        (Shape::new_cylinder(1.0, 2.0, white.clone()), Point3::from([3.0, 1.2, 2.0]), Point3::from([0.0, 0.8, 0.0])),
+        (Shape::new_cylinder(1.0, 2.0, white), Point3::from([-1.0, 4.0, 0.5]), Point3::from([0.2, 2.0, -0.3])),
+    ];
+    let eps = 1e-5;
+    for (shape, origin, target) in cases.iter() {
+        let rec = hit(shape, *origin, *target);
+        assert!(dot(&rec.dpdu, &rec.normal).abs() < 1e-9 && dot(&rec.dpdv, &rec.normal).abs() < 1e-9);
+        let along_u = hit(shape, *origin, rec.p + rec.dpdu * eps);
+        let along_v = hit(shape, *origin, rec.p + rec.dpdv * eps);
+        assert!(((along_u.u - rec.u) / eps - 1.0).abs() < 1e-3 && ((along_u.v - rec.v) / eps).abs() < 1e-3);
+        assert!(((along_v.v - rec.v) / eps - 1.0).abs() < 1e-3 && ((along_v.u - rec.u) / eps).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn test_normal_map() {
+    let (n, dpdu, dpdv) = (Vec3::from([0.0, 0.0, 1.0]), Vec3::from([2.0, 0.0, 0.0]), Vec3::from([0.0, 3.0, 0.0]));
+    let p = Point3::zero();
+    // A flat map leaves the normal alone; one leaning toward +u tips it
+    // over to `dpdu`.
+    let flat = NormalMap::Normal(image(4, |_, _| [128, 128, 255]));
+    assert!(dot(&flat.shading_normal(n, dpdu, dpdv, 0.3, 0.6, &p), &n) > 0.9999);
+    let leaning = NormalMap::Normal(image(4, |_, _| [218, 128, 218]));
+    let bent = leaning.shading_normal(n, dpdu, dpdv, 0.3, 0.6, &p);
+    assert!((bent - unit_vector(Vec3::from([1.0, 0.0, 1.0]))).length() < 0.01);
+    // Mirrored texture coordinates flip the bitangent with `dpdv`.
+    let up = NormalMap::Normal(image(4, |_, _| [128, 255, 128]));
+    assert!(up.shading_normal(n, dpdu, dpdv, 0.5, 0.5, &p).y > 0.99);
+    assert!(up.shading_normal(n, dpdu, -dpdv, 0.5, 0.5, &p).y < -0.99);
+    // Without tangents there is nothing to bend.
+    assert_eq!(n, leaning.shading_normal(n, Vec3::zero(), Vec3::zero(), 0.3, 0.6, &p));
+}
+
+#[test]
+fn test_bump_map() {
+    // Heights rising by `scale` across the unit quad tilt it back against `u`.
+    let ramp = |x: u32, _: u32| [x as u8, x as u8, x as u8];
+    let scale = 0.5;
+    let bump = NormalMapped { base: Arc::new(Material::new_lambertian(Color::ones())), map: NormalMap::Bump { height: image(256, ramp), scale } };
+    let quad = unit_quad(Material::NormalMapped(Box::new(bump)));
+    let rec = hit(&quad, Point3::from([0.3, 0.6, 2.0]), Point3::from([0.3, 0.6, 0.0]));
+    assert!((rec.normal - unit_vector(Vec3::from([-scale, 0.0, 1.0]))).length() < 0.01);
+    // The hit keeps the base material, so the map is applied only once.
+    assert!(matches!(rec.material(), Material::Lambertian(_)));
+
+    // Seen from behind, the bent normal faces the ray too.
+    let back = hit(&quad, Point3::from([0.3, 0.6, -2.0]), Point3::from([0.3, 0.6, 0.0]));
+    assert!((back.normal + rec.normal).length() < 1e-12);
+
+    // Flat height leaves the geometric normal.
+    let flat = NormalMapped { base: Arc::new(Material::new_lambertian(Color::ones())), map: NormalMap::Bump { height: image(8, |_, _| [90, 0, 0]), scale } };
+    let rec = hit(&unit_quad(Material::NormalMapped(Box::new(flat))), Point3::from([0.3, 0.6, 2.0]), Point3::from([0.3, 0.6, 0.0]));
+    assert_eq!(Vec3::from([0.0, 0.0, 1.0]), rec.normal);
+}
@@ -0,0 +1,86 @@
+use float_cmp::approx_eq;
+
+use rayt::{
+    hittable::{Hittable, Shape},
+    material::Material,
+    pdf::{CosinePdf, HittablePdf, MixturePdf, Pdf},
+    utils::PI,
+    vec3::{dot, random_unit_vector, unit_vector, Color, Point3, Vec3},
+};
+
+const N: usize = 200_000;
+
+// Monte Carlo estimate of the integral of `pdf` over the unit sphere.
+fn integrate(pdf: &dyn Pdf) -> f64 {
+    let sum: f64 = (0..N).map(|_| pdf.value(random_unit_vector())).sum();
+    4.0 * PI * sum / N as f64
+}
+
+#[test]
+fn test_cosine_pdf() {
+    let pdf = CosinePdf::new(Vec3::from([0.0, 1.0, 0.0]));
+    assert!(approx_eq!(f64, 1.0 / PI, pdf.value(Vec3::from([0.0, 2.0, 0.0])), ulps = 2));
+    assert_eq!(0.0, pdf.value(Vec3::from([0.0, -1.0, 0.0])));
+    assert!((integrate(&pdf) - 1.0).abs() < 0.02);
+    for _ in 0..100 {
+        assert!(pdf.generate().y >= 0.0);
+    }
+}
+
+#[test]
+fn test_sphere_pdf() {
+    let sphere = Shape::new_sphere(Point3::from([0.0, 0.0, -5.0]), 1.0, Material::new_lambertian(Color::ones()));
+    let pdf = HittablePdf::new(&sphere, Point3::zero());
+    // The sphere covers ~1% of directions, so allow for a few sigma of noise.
+    assert!((integrate(&pdf) - 1.0).abs() < 0.1);
+    let misses = (0..1000).filter(|_| sphere.pdf_value(Point3::zero(), pdf.generate()) == 0.0).count();
+    assert!(misses < 10);
+}
+
+#[test]
+fn test_sphere_pdf_from_inside() {
+    let sphere = Shape::new_sphere(Point3::from([0.2, 0.0, 0.0]), 1.0, Material::new_lambertian(Color::ones()));
+    let pdf = HittablePdf::new(&sphere, Point3::zero());
+    assert!(approx_eq!(f64, 1.0 / (4.0 * PI), pdf.value(Vec3::from([0.0, 1.0, 0.0])), ulps = 2));
+    assert!((integrate(&pdf) - 1.0).abs() < 1e-9);
+    for _ in 0..100 {
+        let d = pdf.generate();
+        assert!(d.length().is_finite() && d.length() > 0.0);
+    }
+}
+
+#[test]
+fn test_quad_pdf() {
+    let quad = Shape::new_quad(
+        Point3::from([-1.0, 2.0, -1.0]),
+        Vec3::from([2.0, 0.0, 0.0]),
+        Vec3::from([0.0, 0.0, 2.0]),
+        Material::new_diffuse_light(Color::ones()),
+    );
+    let pdf = HittablePdf::new(&quad, Point3::zero());
+    assert!((integrate(&pdf) - 1.0).abs() < 0.05);
+    let d = pdf.generate();
+    assert!(dot(&unit_vector(d), &Vec3::from([0.0, 1.0, 0.0])) > 0.0);
+    assert!(approx_eq!(f64, 4.0 / 4.0, quad.pdf_value(Point3::zero(), Vec3::from([0.0, 1.0, 0.0])), ulps = 4));
+}
+
+#[test]
+fn test_triangle_pdf() {
+    let triangle = Shape::new_triangle(
+        Point3::from([-1.0, 1.0, -1.0]),
+        Point3::from([1.0, 1.0, -1.0]),
+        Point3::from([0.0, 1.0, 1.0]),
+        Material::new_diffuse_light(Color::ones()),
+    );
+    let pdf = HittablePdf::new(&triangle, Point3::zero());
+    assert!((integrate(&pdf) - 1.0).abs() < 0.05);
+}
+
+#[test]
+fn test_mixture_pdf() {
+    let up = CosinePdf::new(Vec3::from([0.0, 1.0, 0.0]));
+    let down = CosinePdf::new(Vec3::from([0.0, -1.0, 0.0]));
+    let mixture = MixturePdf::new(&up, &down);
+    assert!(approx_eq!(f64, 0.5 / PI, mixture.value(Vec3::from([0.0, 1.0, 0.0])), ulps = 2));
+    assert!((integrate(&mixture) - 1.0).abs() < 0.02);
+}
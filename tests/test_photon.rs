@@ -0,0 +1,60 @@
+use rayt::{
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::Material,
+    path_tracer::path_color,
+    photon_map::{photon_color, trace_photons, Photon, PhotonMap},
+    ray::Ray,
+    render::Background,
+    vec3::{Color, Point3, Vec3},
+};
+
+#[test]
+fn test_photon_map_range_query() {
+    let photons: Vec<Photon> = (0..2000)
+        .map(|_| Photon { p: Vec3::random(Some([-1.0, 1.0])), direction: Vec3::zero(), power: Color::ones() })
+        .collect();
+    let map = PhotonMap::new(photons.clone());
+    assert_eq!(2000, map.len());
+    for _ in 0..50 {
+        let p = Vec3::random(Some([-1.0, 1.0]));
+        let expected = photons.iter().filter(|ph| (ph.p - p).length() <= 0.3).count();
+        let mut found = 0;
+        map.for_each_within(p, 0.3, |ph| {
+            assert!((ph.p - p).length() <= 0.3);
+            found += 1;
+        });
+        assert_eq!(expected, found);
+    }
+}
+
+#[test]
+fn test_photon_mapping_matches_path_tracer() {
+    // A floor and a back wall under a small downward-facing light, so the
+    // floor sees both direct light and light bounced off the wall.
+    let light = Shape::new_quad(
+        Point3::from([-0.5, 2.0, -0.5]),
+        Vec3::from([1.0, 0.0, 0.0]),
+        Vec3::from([0.0, 0.0, 1.0]),
+        Material::new_diffuse_light(Color::from([4.0, 4.0, 4.0])),
+    );
+    let white = Material::new_lambertian(Color::from([0.7, 0.7, 0.7]));
+    let mut world = HittableList::default();
+    world.add(Shape::new_quad(Point3::from([-5.0, 0.0, -5.0]), Vec3::from([10.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 10.0]), white.clone()));
+    world.add(Shape::new_quad(Point3::from([-5.0, 0.0, -1.0]), Vec3::from([10.0, 0.0, 0.0]), Vec3::from([0.0, 5.0, 0.0]), white));
+    world.add(light.clone());
+    let mut lights = HittableList::default();
+    lights.add(light);
+
+    let background = Background::Solid(Color::zero());
+    let maps = trace_photons(&world, &lights, 200_000, 10);
+    assert!(maps.global.len() > maps.caustic.len());
+    assert!(maps.caustic.is_empty());
+
+    let r = Ray::new(Point3::from([0.0, 1.0, 3.0]), Vec3::from([0.0, -1.0, -2.0]), 0.0);
+    let n = 4000;
+    let path = (0..n).fold(Color::zero(), |sum, _| sum + path_color(&r, &world, &lights, &background, 10)) / n as f64;
+    let photon = (0..n).fold(Color::zero(), |sum, _| sum + photon_color(&r, &world, &lights, &maps, 0.1, &background, 10)) / n as f64;
+    assert!(path.x > 0.0);
+    assert!((photon.x - path.x).abs() < 0.1 * path.x);
+}
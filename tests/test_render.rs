@@ -0,0 +1,212 @@
+use rayt::{
+    camera::Camera,
+    hittable::Shape,
+    hittable_list::HittableList,
+    material::Material,
+    path_tracer::path_color,
+    ray::Ray,
+    render::{ray_color, Background, Integrator, Pixel, RenderSettings, Renderer},
+    vec3::{Color, Point3, Vec3},
+};
+
+fn test_camera(aspect_ratio: f64) -> Camera {
+    Camera::new(
+        Point3::from([0.0, 0.0, 1.0]),
+        Point3::zero(),
+        Vec3::from([0.0, 1.0, 0.0]),
+        90.0,
+        aspect_ratio,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+    )
+}
+
+#[test]
+fn test_framebuffer_size() {
+    let settings = RenderSettings::new(8, 2.0, 1, 1);
+    let fb = Renderer::new(settings).render(&test_camera(2.0), &HittableList::default());
+    assert_eq!(8, fb.width);
+    assert_eq!(4, fb.height);
+    assert_eq!(32, fb.data.len());
+}
+
+#[test]
+fn test_samples_are_averaged() {
+    for spp in [1, 7, 20, 50].iter() {
+        let mut settings = RenderSettings::new(4, 2.0, *spp, 5);
+        settings.background = Background::Gradient { scale: 0.0, offset: 0.0 };
+        let fb = Renderer::new(settings).render(&test_camera(2.0), &HittableList::default());
+        assert!(fb.data.iter().all(|c| (*c - Color::ones()).length() < 1e-9));
+    }
+}
+
+#[test]
+fn test_pixel_gamma() {
+    let p = Pixel::from_color(Color::from([0.0, 0.25, 4.0]));
+    assert_eq!((0, 127, 255), (p.r, p.g, p.b));
+}
+
+#[test]
+fn test_emission_on_black_background() {
+    let mut world = HittableList::default();
+    world.add(Shape::new_sphere(Point3::zero(), 10.0, Material::new_diffuse_light(Color::from([0.5, 0.25, 0.0]))));
+    let mut settings = RenderSettings::new(4, 2.0, 3, 5);
+    settings.background = Background::Solid(Color::zero());
+    let fb = Renderer::new(settings).render(&test_camera(2.0), &world);
+    assert!(fb.data.iter().all(|c| (*c - Color::from([0.5, 0.25, 0.0])).length() < 1e-9));
+}
+
+#[test]
+fn test_integrators_share_the_framing() {
+    // An emitter covering exactly the left half of the view lights the left
+    // half of the pixels, and only those, whichever integrator renders it.
+    let light = Shape::new_quad(
+        Point3::from([-4.0, -4.0, 0.0]),
+        Vec3::from([4.0, 0.0, 0.0]),
+        Vec3::from([0.0, 8.0, 0.0]),
+        Material::new_diffuse_light(Color::ones()),
+    );
+    let mut world = HittableList::default();
+    world.add(light.clone());
+    for integrator in [Integrator::Recursive, Integrator::PathTracer, Integrator::Bidirectional].iter() {
+        let mut settings = RenderSettings::new(8, 2.0, 16, 5);
+        settings.background = Background::Solid(Color::zero());
+        settings.integrator = *integrator;
+        let mut renderer = Renderer::new(settings);
+        renderer.lights.add(light.clone());
+        let fb = renderer.render(&test_camera(2.0), &world);
+        for (i, c) in fb.data.iter().enumerate() {
+            let expected = if i % 8 < 4 { Color::ones() } else { Color::zero() };
+            assert!((*c - expected).length() < 1e-9, "pixel {} is {:?}", i, c);
+        }
+    }
+}
+
+#[test]
+fn test_path_tracer_matches_recursive() {
+    let light = Shape::new_quad(
+        Point3::from([-0.5, 2.0, -0.5]),
+        Vec3::from([1.0, 0.0, 0.0]),
+        Vec3::from([0.0, 0.0, 1.0]),
+        Material::new_diffuse_light(Color::from([4.0, 4.0, 4.0])),
+    );
+    let mut world = HittableList::default();
+    world.add(Shape::new_quad(
+        Point3::from([-5.0, 0.0, -5.0]),
+        Vec3::from([10.0, 0.0, 0.0]),
+        Vec3::from([0.0, 0.0, 10.0]),
+        Material::new_lambertian(Color::from([0.5, 0.5, 0.5])),
+    ));
+    world.add(light.clone());
+    let mut lights = HittableList::default();
+    lights.add(light);
+
+    let background = Background::Solid(Color::zero());
+    let r = Ray::new(Point3::from([0.0, 1.0, 0.0]), Vec3::from([0.1, -1.0, 0.0]), 0.0);
+    let n = 20000;
+    let recursive = (0..n).fold(Color::zero(), |sum, _| sum + ray_color(&r, &world, &lights, &background, 10)) / n as f64;
+    let path = (0..n).fold(Color::zero(), |sum, _| sum + path_color(&r, &world, &lights, &background, 10)) / n as f64;
+    assert!(recursive.x > 0.0);
+    assert!((path.x - recursive.x).abs() < 0.05 * recursive.x);
+}
+
+#[test]
+fn test_mixed_mirror_reflects_lights() {
+    // Half black diffuser, half mirror of albedo 0.5 where the ray lands,
+    // reflecting a light of radiance 4: sampling the light as well must not
+    // dim what the mirror shows.
+    let mut world = HittableList::default();
+    let mirror = Material::new_mix(Material::new_lambertian(Color::zero()), Material::new_metal(Color::ones(), 0.0), 0.5);
+    world.add(Shape::new_quad(Point3::from([-5.0, 0.05, -5.0]), Vec3::from([0.0, 0.0, 10.0]), Vec3::from([10.0, 0.0, 0.0]), mirror));
+    let light = Shape::new_quad(Point3::from([1.5, 2.0, -0.5]), Vec3::from([1.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 1.0]), Material::new_diffuse_light(Color::ones() * 4.0));
+    world.add(light.clone());
+    let mut lights = HittableList::default();
+    lights.add(light);
+    let background = Background::Solid(Color::zero());
+
+    let r = Ray::new(Point3::from([-0.85, 1.05, 0.1]), Vec3::from([1.0, -1.0, 0.0]), 0.0);
+    let n = 4000;
+    let mean = |f: &dyn Fn() -> Color| (0..n).fold(Color::zero(), |sum, _| sum + f()) / n as f64;
+    let expected = Color::ones();
+    let unsampled = mean(&|| path_color(&r, &world, &HittableList::default(), &background, 5));
+    let sampled = mean(&|| path_color(&r, &world, &lights, &background, 5));
+    let recursive = mean(&|| ray_color(&r, &world, &lights, &background, 5));
+    assert!((unsampled - expected).length() < 0.1);
+    assert!((sampled - expected).length() < 0.1);
+    assert!((recursive - expected).length() < 0.15);
+}
+
+#[test]
+fn test_metropolis_splats_where_light_is() {
+    // An emitter covering the top-left quarter of the view.
+    let mut world = HittableList::default();
+    world.add(Shape::new_quad(
+        Point3::from([-3.0, 0.0, 0.0]),
+        Vec3::from([3.0, 0.0, 0.0]),
+        Vec3::from([0.0, 2.0, 0.0]),
+        Material::new_diffuse_light(Color::ones()),
+    ));
+    let mut settings = RenderSettings::new(16, 2.0, 1, 5);
+    settings.background = Background::Solid(Color::zero());
+    settings.integrator = Integrator::Metropolis { mutations_per_pixel: 64, large_step_probability: 0.3 };
+    let fb = Renderer::new(settings).render(&test_camera(2.0), &world);
+
+    // Rows 4 and columns 7-8 straddle the quad edges.
+    let mut lit = Color::zero();
+    for row in 0..8 {
+        for col in 0..16 {
+            if row <= 3 && col <= 6 {
+                lit += fb.get(row, col);
+            } else if row >= 5 || col >= 9 {
+                assert_eq!(Color::zero(), fb.get(row, col));
+            }
+        }
+    }
+    let lit = lit / 28.0;
+    assert!((lit - Color::ones()).length() < 0.05);
+}
+
+#[test]
+fn test_bidirectional_matches_path_tracer() {
+    // A floor and a back wall lit by a small quad, so most
+    // of the image is lit both directly and by light bounced off the wall.
+    // The light itself is out of view.
+    let light = Shape::new_quad(
+        Point3::from([-0.5, 2.0, -0.5]),
+        Vec3::from([1.0, 0.0, 0.0]),
+        Vec3::from([0.0, 0.0, 1.0]),
+        Material::new_diffuse_light(Color::from([4.0, 4.0, 4.0])),
+    );
+    let white = Material::new_lambertian(Color::from([0.7, 0.7, 0.7]));
+    let mut world = HittableList::default();
+    world.add(Shape::new_quad(Point3::from([-5.0, 0.0, -5.0]), Vec3::from([10.0, 0.0, 0.0]), Vec3::from([0.0, 0.0, 10.0]), white.clone()));
+    world.add(Shape::new_quad(Point3::from([-5.0, 0.0, -1.0]), Vec3::from([10.0, 0.0, 0.0]), Vec3::from([0.0, 5.0, 0.0]), white));
+    world.add(light.clone());
+    let cam = Camera::new(
+        Point3::from([0.0, 1.5, 4.0]),
+        Point3::from([0.0, 0.0, 0.0]),
+        Vec3::from([0.0, 1.0, 0.0]),
+        40.0,
+        2.0,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+    );
+
+    let mean = |integrator: Integrator| {
+        let mut settings = RenderSettings::new(64, 2.0, 16, 5);
+        settings.background = Background::Solid(Color::zero());
+        settings.integrator = integrator;
+        let mut renderer = Renderer::new(settings);
+        renderer.lights.add(light.clone());
+        let fb = renderer.render(&cam, &world);
+        fb.data.iter().fold(Color::zero(), |sum, c| sum + *c) / fb.data.len() as f64
+    };
+    let path = mean(Integrator::PathTracer);
+    let bdpt = mean(Integrator::Bidirectional);
+    assert!(path.x > 0.0);
+    assert!((bdpt.x - path.x).abs() < 0.05 * path.x);
+}
@@ -0,0 +1,146 @@
+use rayt::{
+    bsdf::{Bsdf, BsdfFlags},
+    camera::Camera,
+    hittable::{HitRecord, Shape},
+    hittable_list::HittableList,
+    material::{Ior, Material},
+    render::{Background, Integrator, RenderSettings, Renderer},
+    spectrum::{
+        cie_xyz, sample_wavelength, wavelength_pdf, wavelength_weight, xyz_to_film, Channels, Wavelengths,
+        LAMBDA_MAX, LAMBDA_MIN,
+    },
+    vec3::{dot, unit_vector, Color, Point3, Vec3},
+};
+
+#[test]
+fn test_wavelength_sampling() {
+    assert!((sample_wavelength(0.0) - LAMBDA_MIN).abs() < 1e-3);
+    assert!((sample_wavelength(1.0) - LAMBDA_MAX).abs() < 1e-3);
+    // The density integrates to one and matches the sampled distribution.
+    let steps = 10_000;
+    let h = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+    let total: f64 = (0..steps).map(|i| wavelength_pdf(LAMBDA_MIN + (i as f64 + 0.5) * h) * h).sum();
+    assert!((total - 1.0).abs() < 1e-6);
+    let (a, b) = (sample_wavelength(0.3), sample_wavelength(0.3001));
+    assert!(((b - a) * wavelength_pdf(a) - 0.0001).abs() < 1e-7);
+    assert_eq!(0.0, wavelength_pdf(300.0));
+}
+
+#[test]
+fn test_wavelength_weight_averages_to_white() {
+    let n = 100_000;
+    let mean = (0..n)
+        .map(|i| wavelength_weight(sample_wavelength((i as f64 + 0.5) / n as f64)))
+        .fold(Color::zero(), |sum, c| sum + c)
+        / n as f64;
+    assert!((mean - Color::ones()).length() < 1e-3);
+
+    // The luminance curve peaks in the green, reds are red and blues blue.
+    assert!((cie_xyz(555.0).y - 1.0).abs() < 0.01);
+    let red = wavelength_weight(650.0);
+    let blue = wavelength_weight(450.0);
+    assert!(red.x > red.y && red.x > red.z);
+    assert!(blue.z > blue.x && blue.z > blue.y);
+}
+
+#[test]
+fn test_ior_models() {
+    // Catalogue indices at the helium d line.
+    assert!((Ior::BK7.at(587.6) - 1.5168).abs() < 1e-4);
+    assert!((Ior::SF11.at(587.6) - 1.7847).abs() < 1e-4);
+    assert!((Ior::FUSED_SILICA.at(587.6) - 1.4585).abs() < 1e-4);
+    assert!((Ior::DIAMOND.at(587.6) - 2.417).abs() < 2e-3);
+    let cauchy = Ior::Cauchy { a: 1.5, b: 0.004 };
+    assert!((cauchy.at(400.0) - 1.525).abs() < 1e-12);
+    for ior in [Ior::BK7, Ior::SF11, Ior::DIAMOND, cauchy].iter() {
+        assert!(ior.is_dispersive());
+        assert!(ior.at(450.0) > ior.at(650.0));
+    }
+    assert!(!Ior::Constant(1.5).is_dispersive());
+    assert_eq!(1.5, Ior::Constant(1.5).at(400.0));
+}
+
+#[test]
+fn test_prism_bends_blue_more_than_red() {
+    let glass = Material::new_dispersive_dielectric(Ior::SF11);
+    let mut rec = HitRecord::new();
+    rec.normal = Vec3::from([0.0, 0.0, 1.0]);
+    rec.front_face = true;
+    let wo = unit_vector(Vec3::from([1.0, 0.0, 1.0]));
+    let refract = |rec: &HitRecord| glass.sample(rec, wo, [0.999, 0.5, 0.5]).unwrap();
+
+    // Without a wavelength the glass refracts like plain glass at the
+    // reference wavelength.
+    let plain = refract(&rec);
+    assert!(!plain.flags.contains(BsdfFlags::DISPERSIVE));
+    rec.channels = Channels::Wavelength(450.0);
+    let blue = refract(&rec);
+    rec.channels = Channels::Wavelength(650.0);
+    let red = refract(&rec);
+    assert!(blue.flags.contains(BsdfFlags::DISPERSIVE));
+    // The refracted ray leans toward -x; blue ends up closer to the normal.
+    let normal = Vec3::from([0.0, 0.0, -1.0]);
+    assert!(dot(&blue.wi, &normal) > dot(&red.wi, &normal));
+}
+
+#[test]
+fn test_dispersion_keeps_white_light_white() {
+    // A diamond against a uniform white sky scatters every wavelength back
+    // out, so the image averages to white.
+    let mut world = HittableList::default();
+    world.add(Shape::new_sphere(Point3::zero(), 0.5, Material::new_dispersive_dielectric(Ior::DIAMOND)));
+    for spectral in [false, true] {
+        let mean = mean_color(&world, Background::Solid(Color::ones()), spectral);
+        assert!((mean - Color::ones()).length() < 0.03);
+    }
+}
+
+/// Mean of a small path traced render of `world` looking at the origin.
+fn mean_color(world: &HittableList, background: Background, spectral: bool) -> Color {
+    let cam = Camera::new(
+        Point3::from([0.0, 0.0, 2.0]),
+        Point3::zero(),
+        Vec3::from([0.0, 1.0, 0.0]),
+        20.0,
+        1.0,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+    );
+    let mut settings = RenderSettings::new(32, 1.0, 64, 50);
+    settings.background = background;
+    settings.integrator = Integrator::PathTracer;
+    settings.spectral = spectral;
+    let fb = Renderer::new(settings).render(&cam, world);
+    fb.data.iter().fold(Color::zero(), |sum, c| sum + *c) / fb.data.len() as f64
+}
+
+#[test]
+fn test_uplift_round_trip() {
+    // Integrating an uplifted spectrum against the color matching functions
+    // gives back the color it came from.
+    let n = 30_000;
+    for rgb in [[1.0, 1.0, 1.0], [0.8, 0.3, 0.2], [0.1, 0.6, 0.3], [0.2, 0.3, 0.9], [2.0, 1.5, 0.5]] {
+        let rgb = Color::from(rgb);
+        let xyz = (0..n)
+            .map(|i| {
+                let w = Wavelengths::sample((i as f64 + 0.5) / n as f64);
+                w.to_xyz(w.uplift(rgb))
+            })
+            .fold(Vec3::zero(), |sum, c| sum + c)
+            / n as f64;
+        assert!((xyz_to_film(xyz) - rgb).length() < 0.05 * rgb.length());
+    }
+}
+
+#[test]
+fn test_spectral_mode_matches_rgb() {
+    // A colored diffuse sphere under a tinted sky renders the same either way.
+    let mut world = HittableList::default();
+    world.add(Shape::new_sphere(Point3::zero(), 0.5, Material::new_lambertian(Color::from([0.8, 0.3, 0.2]))));
+    let background = Background::Solid(Color::from([0.6, 0.8, 1.0]));
+    let rgb = mean_color(&world, background, false);
+    let spectral = mean_color(&world, background, true);
+    assert!((rgb - spectral).length() < 0.03);
+}